@@ -0,0 +1,306 @@
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+use std::sync::{ Arc, Condvar, Mutex, RwLock };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::sync::mpsc::{ channel, Receiver, TryRecvError };
+use std::collections::HashMap;
+use xcb::{ self, Atom };
+use error::Error;
+use metrics::Metrics;
+use ::{ run, Clipboard, Context, MetricsSink, RequestorPolicy, SetMap, Released, INCR_CHUNK_SIZE, read_setmap, write_setmap };
+
+type ExitCallback = Box<dyn FnOnce(Option<Error>) + Send>;
+
+/// Configure and create a `Clipboard`.
+#[derive(Clone, Debug, Default)]
+pub struct ClipboardBuilder {
+    display: Option<String>,
+    extra_atoms: Vec<String>,
+    event_mask: u32,
+    auto_restore_on_reconnect: bool,
+    cleanup_properties: bool,
+    poll_backoff: Option<(Duration, Duration)>,
+    incr_chunk_size: Option<usize>,
+    reconnect_on_loss: Option<(u32, Duration)>,
+    persist_on_exit: Option<Duration>,
+    metrics: Option<Metrics>,
+    on_setter_exit: Option<ExitHook>
+}
+
+/// The `on_setter_exit` callback, shared by every worker built from a builder.
+#[derive(Clone)]
+struct ExitHook(Arc<Mutex<Option<ExitCallback>>>);
+
+impl ExitHook {
+    fn fire(&self, error: Option<Error>) {
+        let callback = match self.0.lock() {
+            Ok(mut callback) => callback.take(),
+            Err(err) => err.into_inner().take()
+        };
+        if let Some(callback) = callback {
+            callback(error);
+        }
+    }
+}
+
+impl fmt::Debug for ExitHook {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ExitHook(..)")
+    }
+}
+
+/// Marks the worker dead once it stops, and reports it if it unwinds instead of returning.
+struct ExitGuard {
+    hook: Option<ExitHook>,
+    alive: Arc<AtomicBool>
+}
+
+impl Drop for ExitGuard {
+    fn drop(&mut self) {
+        self.alive.store(false, Ordering::SeqCst);
+        if let Some(ref hook) = self.hook {
+            if thread::panicking() {
+                hook.fire(Some(Error::WorkerPanicked));
+            }
+        }
+    }
+}
+
+impl ClipboardBuilder {
+    pub fn new() -> Self {
+        ClipboardBuilder::default()
+    }
+
+    /// connect to `displayname`, e.g. `":1"`, rather than `$DISPLAY`, which `None` goes back to.
+    pub fn display<'a, T: Into<Option<&'a str>>>(mut self, displayname: T) -> Self {
+        self.display = displayname.into().map(str::to_owned);
+        self
+    }
+
+    /// intern `names`, e.g. `"text/html"`, along with the atoms we use, so they're
+    /// looked up with `Atoms::extra` rather than a round trip each.
+    pub fn extra_atoms(mut self, names: &[&str]) -> Self {
+        self.extra_atoms.extend(names.iter().map(|&name| name.to_owned()));
+        self
+    }
+
+    /// select `event_mask` on the windows of both contexts as well, see
+    /// `Context::new_with_event_mask`.
+    pub fn event_mask(mut self, event_mask: u32) -> Self {
+        self.event_mask |= event_mask;
+        self
+    }
+
+    /// publish everything stored so far again after `Clipboard::reconnect`.
+    pub fn auto_restore_on_reconnect(mut self, enable: bool) -> Self {
+        self.auto_restore_on_reconnect = enable;
+        self
+    }
+
+    /// run `Context::cleanup_properties` on every context before using it,
+    /// reconnected ones included.
+    pub fn cleanup_properties(mut self, enable: bool) -> Self {
+        self.cleanup_properties = enable;
+        self
+    }
+
+    /// poll after `min` at first while loading, and twice as long each time
+    /// nothing arrived up to `max`, see `Context::set_poll_backoff`.
+    pub fn poll_backoff(mut self, min: Duration, max: Duration) -> Self {
+        self.poll_backoff = Some((min, max));
+        self
+    }
+
+    /// send INCR transfers in chunks of `size` bytes rather than `INCR_CHUNK_SIZE`.
+    ///
+    /// Sizes past what fits in a single request to the server are cut down to that.
+    pub fn incr_chunk_size(mut self, size: usize) -> Self {
+        self.incr_chunk_size = Some(size);
+        self
+    }
+
+    /// poll every `duration` while loading, rather than backing off from 2ms to 50ms.
+    ///
+    /// Shorter answers sooner, at the cost of more wakeups. It's `poll_backoff`
+    /// without the backoff.
+    pub fn poll_duration(self, duration: Duration) -> Self {
+        self.poll_backoff(duration, duration)
+    }
+
+    /// have the worker connect again when it loses its connection, e.g. to an X server
+    /// restart, and take back every selection stored.
+    ///
+    /// It tries up to `attempts` times, waiting `backoff` at first and twice as long
+    /// after each failure. The last failure is what stops it, see `on_setter_exit`.
+    /// Only the worker reconnects: `getter` is left as is, loads need `Clipboard::reconnect`.
+    pub fn reconnect_on_loss(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.reconnect_on_loss = Some((attempts, backoff));
+        self
+    }
+
+    /// when the `Clipboard` is dropped owning `CLIPBOARD`, hand what's stored in it
+    /// to the clipboard manager, if one runs, so it can still be pasted afterwards.
+    ///
+    /// Dropping blocks up to `timeout` while the manager copies it.
+    pub fn persist_on_exit(mut self, timeout: Duration) -> Self {
+        self.persist_on_exit = Some(timeout);
+        self
+    }
+
+    /// report transfer sizes and durations to `sink`, both of loads and of
+    /// values served, see `MetricsSink`.
+    pub fn metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(Metrics(sink));
+        self
+    }
+
+    /// call `callback` once the thread serving stored selections stops, with the
+    /// error that stopped it, or `None` when the `Clipboard` was dropped.
+    ///
+    /// It's called at most once, for the first worker to stop. After
+    /// `Clipboard::reconnect` that's the replaced one, reporting `None`.
+    pub fn on_setter_exit<F>(mut self, callback: F) -> Self
+        where F: FnOnce(Option<Error>) + Send + 'static
+    {
+        self.on_setter_exit = Some(ExitHook(Arc::new(Mutex::new(Some(Box::new(callback))))));
+        self
+    }
+
+    pub(crate) fn restores_on_reconnect(&self) -> bool {
+        self.auto_restore_on_reconnect
+    }
+
+    pub(crate) fn persist_timeout(&self) -> Option<Duration> {
+        self.persist_on_exit
+    }
+
+    pub(crate) fn display_name(&self) -> Option<&str> {
+        self.display.as_deref()
+    }
+
+    fn connect(&self) -> Result<Context, Error> {
+        let extra_atoms = self.extra_atoms.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut context = Context::open(self.display_name(), &extra_atoms, self.event_mask)?;
+        if let Some(Metrics(ref sink)) = self.metrics {
+            context.set_metrics(Arc::clone(sink));
+        }
+        if let Some((min, max)) = self.poll_backoff {
+            context.set_poll_backoff(min, max);
+        }
+        if self.cleanup_properties {
+            context.cleanup_properties()?;
+        }
+        Ok(context)
+    }
+
+    /// Create Clipboard.
+    pub fn build(self) -> Result<Clipboard, Error> {
+        let getter = self.connect()?;
+        let setter = Arc::new(self.connect()?);
+        let setter2 = Arc::clone(&setter);
+        let setmap = Arc::new(RwLock::new(HashMap::new()));
+        let setmap2 = Arc::clone(&setmap);
+        let policy = Arc::new(RwLock::new(RequestorPolicy::default()));
+        let policy2 = Arc::clone(&policy);
+        let released = Arc::new((Mutex::new(()), Condvar::new()));
+        let released2 = Arc::clone(&released);
+
+        let serving = Arc::new(RwLock::new(Arc::clone(&setter)));
+        let serving2 = Arc::clone(&serving);
+
+        let (sender, receiver) = channel();
+        let chunk_size = self.incr_chunk_size.unwrap_or(INCR_CHUNK_SIZE);
+        let (builder, reconnect) = (self.clone(), self.reconnect_on_loss);
+        let alive = Arc::new(AtomicBool::new(true));
+        let guard = ExitGuard { hook: self.on_setter_exit.clone(), alive: Arc::clone(&alive) };
+        thread::spawn(move || {
+            let mut context = setter2;
+            let result = loop {
+                let max_length = context.connection.get_maximum_request_length() as usize * 4;
+                let result = run::run(&context, &setmap2, &policy2, &released2, max_length, chunk_size, &receiver);
+                let (attempts, backoff) = match (result, reconnect) {
+                    (Err(_), Some(reconnect)) => reconnect,
+                    (result, _) => break result
+                };
+
+                match reestablish(&builder, &setmap2, &released2, &receiver, attempts, backoff) {
+                    Ok(Some(fresh)) => {
+                        context = fresh;
+                        if let Ok(mut serving) = serving2.write() {
+                            *serving = Arc::clone(&context);
+                        }
+                    },
+                    Ok(None) => break Ok(()),
+                    Err(err) => break Err(err)
+                }
+            };
+            if let Some(ref hook) = guard.hook {
+                hook.fire(result.err());
+            }
+        });
+
+        Ok(Clipboard { getter, setter, serving, setmap, policy, released, alive, send: sender, default_timeout: None, builder: self })
+    }
+}
+
+/// connect the worker again, retrying up to `attempts` times, and take back every
+/// selection stored. Selections someone else took meanwhile are forgotten.
+///
+/// Returns `None` if the `Clipboard` went away while retrying.
+fn reestablish(builder: &ClipboardBuilder, setmap: &SetMap, released: &Released, receiver: &Receiver<Atom>, attempts: u32, mut backoff: Duration)
+    -> Result<Option<Arc<Context>>, Error>
+{
+    let mut error = Error::WorkerDead;
+    'attempts: for _ in 0..attempts {
+        thread::sleep(backoff);
+        backoff *= 2;
+
+        loop {
+            match receiver.try_recv() {
+                Ok(_) => (),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return Ok(None)
+            }
+        }
+
+        let context = match builder.connect() {
+            Ok(context) => context,
+            Err(err) => {
+                error = err;
+                continue
+            }
+        };
+
+        let selections = read_setmap(setmap)
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        // server_time reads this context's events, safe as nothing else does before the
+        // worker runs on it. A failure here is one more attempt gone, like a failed connect.
+        let time = match context.server_time() {
+            Ok(time) => time,
+            Err(err) => {
+                error = err;
+                continue
+            }
+        };
+        for selection in selections {
+            xcb::set_selection_owner(&context.connection, context.window, selection, time);
+            let owner = match xcb::get_selection_owner(&context.connection, selection).get_reply() {
+                Ok(reply) => reply.owner(),
+                Err(err) => {
+                    error = err.into();
+                    continue 'attempts
+                }
+            };
+            if owner != context.window {
+                run::forget(setmap, released, selection);
+            } else if let Some(entry) = write_setmap(setmap).get_mut(&selection) {
+                entry.acquired = time;
+            }
+        }
+        return Ok(Some(Arc::new(context)));
+    }
+    Err(error)
+}
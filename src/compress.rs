@@ -0,0 +1,34 @@
+use std::io::{ Read, Write };
+use std::time::Duration;
+use flate2::Compression;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use xcb::Atom;
+use error::Error;
+use ::Clipboard;
+
+impl Clipboard {
+    /// store value zlib compressed, for another `load_compressed` to read.
+    ///
+    /// Only useful between instances of the same app, nobody else will
+    /// know what to make of the target.
+    pub fn store_compressed<T: AsRef<[u8]>>(&self, selection: Atom, target: Atom, value: T)
+        -> Result<(), Error>
+    {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(value.as_ref()).map_err(Error::Compression)?;
+        let value = encoder.finish().map_err(Error::Compression)?;
+        self.store(selection, target, value)
+    }
+
+    /// load value stored by `store_compressed` and decompress it.
+    pub fn load_compressed<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let buff = self.load(selection, target, property, timeout)?;
+        let mut value = Vec::new();
+        ZlibDecoder::new(&buff[..]).read_to_end(&mut value).map_err(Error::Compression)?;
+        Ok(value)
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, AtomicUsize, Ordering };
+
+/// Progress and cancellation of a single load, see `Clipboard::load_controlled`.
+///
+/// It's meant to be shared with another thread, e.g. a progress bar
+/// with a cancel button, while the load runs.
+#[derive(Debug, Default)]
+pub struct LoadControl {
+    bytes_so_far: AtomicUsize,
+    size_hint: AtomicUsize,
+    cancelled: AtomicBool
+}
+
+impl LoadControl {
+    pub fn new() -> LoadControl {
+        LoadControl::default()
+    }
+
+    /// bytes received so far.
+    pub fn bytes_so_far(&self) -> usize {
+        self.bytes_so_far.load(Ordering::Relaxed)
+    }
+
+    /// total size announced by the owner, once known.
+    pub fn size_hint(&self) -> Option<usize> {
+        match self.size_hint.load(Ordering::Relaxed) {
+            0 => None,
+            size => Some(size)
+        }
+    }
+
+    /// stop the load, it returns `Error::Cancelled` shortly after.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_progress(&self, bytes: usize) {
+        self.bytes_so_far.store(bytes, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_size_hint(&self, size: usize) {
+        self.size_hint.store(size, Ordering::Relaxed);
+    }
+}
+
+/// Cancels a blocking call from elsewhere, e.g. `Clipboard::store_and_wait`
+/// from a signal handler. Clones share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,48 @@
+use std::time::{ Duration, Instant };
+
+/// A point in time to give up at, so loads made one after another share one budget
+/// rather than each getting the full timeout, see `Clipboard::load_until`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Deadline(Option<Instant>);
+
+impl Deadline {
+    /// `timeout` from now, `None` never passes.
+    pub fn after(timeout: Option<Duration>) -> Deadline {
+        Deadline(timeout.map(|timeout| Instant::now() + timeout))
+    }
+
+    pub fn never() -> Deadline {
+        Deadline(None)
+    }
+
+    pub fn instant(&self) -> Option<Instant> {
+        self.0
+    }
+
+    /// time left until it passes, zero once it has and `None` if it never does.
+    pub fn remaining(&self) -> Option<Duration> {
+        self.0.map(|deadline| deadline.saturating_duration_since(Instant::now()))
+    }
+
+    pub fn has_passed(&self) -> bool {
+        self.0.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+impl From<Instant> for Deadline {
+    fn from(deadline: Instant) -> Deadline {
+        Deadline(Some(deadline))
+    }
+}
+
+impl From<Duration> for Deadline {
+    fn from(timeout: Duration) -> Deadline {
+        Deadline::after(Some(timeout))
+    }
+}
+
+impl From<Option<Duration>> for Deadline {
+    fn from(timeout: Option<Duration>) -> Deadline {
+        Deadline::after(timeout)
+    }
+}
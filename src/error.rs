@@ -1,9 +1,21 @@
-use xcb::Atom;
+use xcb::{ self, Atom, Window };
 use xcb::base::{ ConnError, GenericError };
 use std::fmt;
+use std::time::Duration;
+use std::io;
+use std::string::FromUtf8Error;
 use std::sync::mpsc::SendError;
 use std::error::Error as StdError;
 
+/// What an `Error::Timeout` was waiting for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TimeoutInfo {
+    pub selection: Atom,
+    /// `xcb::ATOM_NONE` when it wasn't waiting for a particular target.
+    pub target: Atom,
+    pub elapsed: Duration
+}
+
 #[must_use]
 #[derive(Debug)]
 pub enum Error {
@@ -11,9 +23,24 @@ pub enum Error {
     XcbConn(ConnError),
     XcbGeneric(GenericError),
     Lock,
-    Timeout,
-    Owner,
+    Timeout(TimeoutInfo),
+    Cancelled,
+    Owner { actual: Window },
+    OwnerChanged,
+    NoOwner,
+    Cleared,
+    PropertyPoolExhausted,
     UnexpectedType(Atom),
+    NoMatchingTarget,
+    SelfConversionUnsupported,
+    TooLargeForDirectTransfer,
+    BenchmarkMismatch,
+    WorkerPanicked,
+    WorkerDead,
+    Io(io::Error),
+    InvalidUtf8(FromUtf8Error),
+    #[cfg(feature = "compression")]
+    Compression(io::Error),
 
     #[doc(hidden)]
     __Unknown
@@ -27,9 +54,27 @@ impl fmt::Display for Error {
             XcbConn(e) => write!(f, "XCB connection error: {:?}", e),
             XcbGeneric(e) => write!(f, "XCB generic error: {:?}", e),
             Lock => write!(f, "XCB: Lock is poisoned"),
-            Timeout => write!(f, "Selection timed out"),
-            Owner => write!(f, "Failed to set new owner of XCB selection"),
+            Timeout(TimeoutInfo { selection, target, elapsed }) if *target == xcb::ATOM_NONE =>
+                write!(f, "Timed out after {} ms waiting on selection {}", elapsed.as_millis(), selection),
+            Timeout(TimeoutInfo { selection, target, elapsed }) =>
+                write!(f, "Timed out after {} ms waiting for target {} on selection {}", elapsed.as_millis(), target, selection),
+            Cancelled => write!(f, "Load was cancelled"),
+            Owner { actual } => write!(f, "Failed to set new owner of XCB selection, owned by window {}", actual),
+            OwnerChanged => write!(f, "Selection owner changed during the transfer"),
+            NoOwner => write!(f, "Selection has no owner"),
+            Cleared => write!(f, "Selection was cleared while waiting for it"),
+            PropertyPoolExhausted => write!(f, "All pooled properties are in use"),
             UnexpectedType(target) => write!(f, "Unexpected Reply type: {}", target),
+            NoMatchingTarget => write!(f, "Selection owner offers none of the targets asked for"),
+            SelfConversionUnsupported => write!(f, "Selection is owned by the window loading it, store through the setter instead"),
+            TooLargeForDirectTransfer => write!(f, "Selection data is too large to read without INCR"),
+            BenchmarkMismatch => write!(f, "Data loaded back differs from the data stored"),
+            WorkerPanicked => write!(f, "Selection serving thread panicked"),
+            WorkerDead => write!(f, "Selection serving thread has stopped"),
+            Io(e) => write!(f, "IO error: {}", e),
+            InvalidUtf8(e) => write!(f, "Selection text is not valid UTF-8: {}", e),
+            #[cfg(feature = "compression")]
+            Compression(e) => write!(f, "Compression error: {}", e),
             __Unknown => unreachable!()
         }
     }
@@ -42,7 +87,11 @@ impl StdError for Error {
             Set(e) => Some(e),
             XcbConn(e) => Some(e),
             XcbGeneric(e) => Some(e),
-            Lock | Timeout | Owner | UnexpectedType(_) => None,
+            Io(e) => Some(e),
+            InvalidUtf8(e) => Some(e),
+            #[cfg(feature = "compression")]
+            Compression(e) => Some(e),
+            Lock | Timeout(_) | Cancelled | Owner { .. } | OwnerChanged | NoOwner | Cleared | PropertyPoolExhausted | UnexpectedType(_) | NoMatchingTarget | SelfConversionUnsupported | TooLargeForDirectTransfer | BenchmarkMismatch | WorkerPanicked | WorkerDead => None,
             __Unknown => unreachable!()
         }
     }
@@ -61,3 +110,5 @@ macro_rules! define_from {
 define_from!(Set from SendError<Atom>);
 define_from!(XcbConn from ConnError);
 define_from!(XcbGeneric from GenericError);
+define_from!(Io from io::Error);
+define_from!(InvalidUtf8 from FromUtf8Error);
@@ -0,0 +1,51 @@
+use std::fs::File;
+use std::io::{ BufWriter, Write };
+use std::path::Path;
+use std::time::Duration;
+#[cfg(feature = "mmap")]
+use std::sync::Arc;
+#[cfg(feature = "mmap")]
+use memmap2::Mmap;
+use xcb::{ self, Atom };
+use error::Error;
+use sink::{ Sink, WriteSink };
+use ::{ Clipboard, Deadline, LoadOptions };
+
+impl Clipboard {
+    /// store the contents of the file at `path`, mapped into memory rather than read.
+    ///
+    /// Large files go out through INCR a chunk at a time, straight from the mapping.
+    /// The file must not be truncated while it's stored.
+    #[cfg(feature = "mmap")]
+    pub fn store_file(&self, selection: Atom, target: Atom, path: &Path) -> Result<(), Error> {
+        let file = File::open(path)?;
+        // Safety: the mapping is only read, and changes to the file are the caller's to avoid.
+        let map = unsafe { Mmap::map(&file)? };
+        self.store_shared(selection, target, Arc::new(map))
+    }
+
+    /// load value into the file at `dest`, writing INCR chunks out as they arrive
+    /// instead of keeping them.
+    ///
+    /// Returns the number of bytes written, an empty file is left where `load`
+    /// would return nothing.
+    pub fn load_file<T>(&self, selection: Atom, target: Atom, property: Atom, dest: &Path, timeout: T)
+        -> Result<usize, Error>
+        where T: Into<Option<Duration>>
+    {
+        let getter = &self.getter;
+        let mut sink = WriteSink::new(BufWriter::new(File::create(dest)?));
+
+        let time = getter.server_time()?;
+        if getter.request_conversion(selection, target, property, time)?.is_none() {
+            getter.process_event(getter.window, &mut sink, selection, target, property, LoadOptions {
+                deadline: Deadline::after(timeout.into()), ..LoadOptions::default()
+            })?;
+            xcb::delete_property(&getter.connection, getter.window, property);
+            getter.connection.flush();
+        }
+
+        sink.writer.flush()?;
+        Ok(sink.len())
+    }
+}
@@ -0,0 +1,62 @@
+use std::time::Duration;
+use xcb::{ self, Atom, Window };
+use error::Error;
+use ::Context;
+
+/// Read-only access to selections.
+///
+/// Unlike `Clipboard`, this holds a single connection and no worker thread,
+/// so it is cheap enough for things like status bars that only look at what
+/// other clients offer.
+pub struct Inspector {
+    pub context: Context
+}
+
+impl Inspector {
+    /// Create Inspector.
+    pub fn new() -> Result<Self, Error> {
+        Ok(Inspector { context: Context::new(None)? })
+    }
+
+    /// current owner of the selection, `xcb::NONE` if there is none.
+    pub fn owner(&self, selection: Atom) -> Result<Window, Error> {
+        xcb::get_selection_owner(&self.context.connection, selection)
+            .get_reply()
+            .map(|reply| reply.owner())
+            .map_err(Into::into)
+    }
+
+    /// atoms of the targets the owner offers, for matching against atoms interned
+    /// up front without looking up a name each.
+    pub fn load_target_atoms<T>(&self, selection: Atom, timeout: T)
+        -> Result<Vec<Atom>, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.context.load_target_atoms(selection, timeout.into())
+    }
+
+    /// names of the targets the owner offers.
+    pub fn target_names<T>(&self, selection: Atom, timeout: T)
+        -> Result<Vec<String>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let atoms = self.load_target_atoms(selection, timeout)?;
+        let cookies = atoms.iter()
+            .map(|&atom| xcb::get_atom_name(&self.context.connection, atom))
+            .collect::<Vec<_>>();
+
+        cookies.into_iter()
+            .map(|cookie| cookie.get_reply()
+                .map(|reply| reply.name().to_owned())
+                .map_err(Into::into))
+            .collect()
+    }
+
+    /// load value.
+    pub fn load<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.context.load(selection, target, property, timeout.into())
+    }
+}
@@ -1,20 +1,346 @@
 pub extern crate xcb;
+#[cfg(feature = "compression")]
+extern crate flate2;
+#[cfg(feature = "bytes")]
+extern crate bytes;
+#[cfg(feature = "serde")]
+extern crate serde;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+
+// Without the `log` feature, arguments are still checked but never evaluated.
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ( $( $arg:tt )+ ) => { if false { let _ = format_args!($( $arg )+); } };
+}
+#[cfg(not(feature = "log"))]
+macro_rules! debug {
+    ( $( $arg:tt )+ ) => { if false { let _ = format_args!($( $arg )+); } };
+}
 
 pub mod error;
 mod run;
+mod pool;
+mod inspector;
+mod builder;
+mod policy;
+mod control;
+mod watch;
+mod snapshot;
+mod sink;
+mod file;
+mod stream;
+mod deadline;
+mod load_handle;
+mod metrics;
+#[cfg(feature = "compression")]
+mod compress;
+#[cfg(feature = "bytes")]
+mod load_bytes;
+#[cfg(feature = "async")]
+mod load_async;
 
+use std::cmp;
+use std::mem;
+use std::borrow::Cow;
 use std::thread;
 use std::time::{ Duration, Instant };
-use std::sync::{ Arc, RwLock };
-use std::sync::mpsc::{ Sender, channel };
+use std::sync::{ Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard };
+use std::sync::mpsc::{ self, Sender, Receiver };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use std::hash::{ Hash, Hasher };
 use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::os::unix::io::{ AsRawFd, RawFd };
 use xcb::{ Connection, Window, Atom };
 use xcb::base::ConnError;
-use error::Error;
+use error::{ Error, TimeoutInfo };
+use pool::PropertyPool;
+use policy::SharedPolicy;
+use sink::Sink;
+
+pub use pool::{ PooledProperty, PROPERTY_POOL_LIMIT };
+pub use inspector::Inspector;
+pub use builder::ClipboardBuilder;
+pub use policy::RequestorPolicy;
+pub use control::{ LoadControl, CancelToken };
+pub use watch::{ WatchHandle, WatchEvent, WatchEvents };
+pub use snapshot::ClipboardSnapshot;
+pub use stream::LoadStream;
+pub use deadline::Deadline;
+pub use load_handle::LoadHandle;
+pub use metrics::MetricsSink;
+#[cfg(feature = "async")]
+pub use load_async::LoadFuture;
 
 pub const INCR_CHUNK_SIZE: usize = 4000;
 const POLL_DURATION: u64 = 50;
-type SetMap = Arc<RwLock<HashMap<Atom, (Atom, Vec<u8>)>>>;
+/// Where loads start backing off from, see `Context::set_poll_backoff`.
+const MIN_POLL_DURATION: u64 = 2;
+/// Most a load reserves up front for the size an INCR owner announced.
+const INCR_RESERVE_LIMIT: usize = 64 * 1024 * 1024;
+/// How long `try_load` waits for the owner to answer.
+const TRY_LOAD_WINDOW: u64 = 100;
+type SetMap = Arc<RwLock<HashMap<Atom, Entry>>>;
+/// the context the worker currently serves from.
+type Serving = Arc<RwLock<Arc<Context>>>;
+/// signalled whenever the setter stops owning a selection.
+type Released = Arc<(Mutex<()>, Condvar)>;
+
+type Provider = Arc<dyn Fn() -> Receiver<Vec<u8>> + Send + Sync>;
+
+type Dynamic = Box<dyn FnMut(Window, Atom) -> Option<Vec<u8>> + Send>;
+
+/// Computes the data of a target from a shared source, see `Clipboard::store_derived`.
+pub type Derive = fn(&[u8]) -> Vec<u8>;
+
+enum Value {
+    Bytes(Vec<u8>),
+    /// served with its own type and format, 32-bit data is in native byte order.
+    Typed { type_: Atom, format: u8, data: Vec<u8> },
+    /// computed from the shared source on each request.
+    Derived(Arc<Vec<u8>>, Derive),
+    /// computed on request, see `Clipboard::store_deferred`.
+    Deferred(Provider),
+    /// bytes kept somewhere other than a `Vec`, e.g. a mapped file.
+    Shared(Arc<dyn AsRef<[u8]> + Send + Sync>),
+    /// computed for each requestor, see `Clipboard::store_dynamic`.
+    Dynamic(Mutex<Dynamic>)
+}
+
+impl Value {
+    fn bytes(&self) -> Option<&[u8]> {
+        match *self {
+            Value::Bytes(ref value) | Value::Typed { data: ref value, .. } => Some(value),
+            Value::Shared(ref value) => Some((**value).as_ref()),
+            Value::Derived(..) | Value::Deferred(_) | Value::Dynamic(_) => None
+        }
+    }
+
+    /// size of the data served, `None` while it's not known yet.
+    fn len(&self) -> Option<usize> {
+        match *self {
+            Value::Bytes(ref value) | Value::Typed { data: ref value, .. } => Some(value.len()),
+            Value::Shared(ref value) => Some((**value).as_ref().len()),
+            Value::Derived(ref source, derive) => Some(derive(source).len()),
+            Value::Deferred(_) | Value::Dynamic(_) => None
+        }
+    }
+}
+
+struct Entry {
+    targets: Vec<(Atom, Value)>,
+    /// relinquish the selection after it has been served once.
+    ephemeral: bool,
+    /// the only requestor window served, if any.
+    requestor: Option<Window>,
+    /// relinquish the selection once this has passed.
+    expires: Option<Instant>,
+    pastes: Arc<Pastes>,
+    /// called with the requestor of each request served.
+    on_request: Option<Mutex<OnRequest>>,
+    /// told how far each INCR transfer got.
+    progress: Option<Mutex<Sender<StoreProgress>>>,
+    /// server time the selection was acquired at, answered for `TIMESTAMP`.
+    acquired: xcb::Timestamp,
+    fingerprint: u64
+}
+
+type OnRequest = Box<dyn FnMut(Window) + Send>;
+
+/// How `Context::process_event` waits for the data and what it takes.
+#[derive(Clone, Copy)]
+struct LoadOptions<'a> {
+    deadline: Deadline,
+    control: Option<&'a LoadControl>,
+    /// take data of any type, not only what `target` is expected to come as.
+    any_type: bool,
+    /// wait for XFIXES owner changes, whose events start at this base, rather than
+    /// polling for the answer.
+    xfixes_event_base: Option<u8>
+}
+
+impl<'a> Default for LoadOptions<'a> {
+    fn default() -> Self {
+        LoadOptions { deadline: Deadline::never(), control: None, any_type: false, xfixes_event_base: None }
+    }
+}
+
+/// Counts the requests served from an entry.
+#[derive(Default)]
+struct Pastes {
+    count: Mutex<usize>,
+    condvar: Condvar
+}
+
+impl Pastes {
+    fn record(&self) {
+        if let Ok(mut count) = self.count.lock() {
+            *count += 1;
+        }
+        self.condvar.notify_all();
+    }
+}
+
+impl Entry {
+    fn new(targets: Vec<(Atom, Value)>) -> Entry {
+        let fingerprint = fingerprint(&targets);
+        Entry { targets, ephemeral: false, requestor: None, expires: None, pastes: Arc::default(), on_request: None, progress: None, acquired: xcb::CURRENT_TIME, fingerprint }
+    }
+
+    /// count a request served from this entry.
+    fn served(&self, requestor: Window) {
+        self.pastes.record();
+        if let Some(ref on_request) = self.on_request {
+            if let Ok(mut on_request) = on_request.lock() {
+                on_request(requestor);
+            }
+        }
+    }
+
+    /// report a chunk sent, a receiver that's gone doesn't stop the transfer.
+    fn progressed(&self, progress: StoreProgress) {
+        if let Some(ref sender) = self.progress {
+            if let Ok(sender) = sender.lock() {
+                let _ = sender.send(progress);
+            }
+        }
+    }
+
+    fn get(&self, target: Atom) -> Option<&Value> {
+        self.targets.iter()
+            .find(|&&(t, _)| t == target)
+            .map(|(_, value)| value)
+    }
+
+    /// replace a deferred target with the value its provider produced.
+    fn resolve(&mut self, target: Atom, provider: &Provider, value: Vec<u8>) {
+        for &mut (t, ref mut slot) in &mut self.targets {
+            let is_same = match *slot {
+                Value::Deferred(ref p) => t == target && Arc::ptr_eq(p, provider),
+                Value::Bytes(_) | Value::Typed { .. } | Value::Derived(..) | Value::Shared(_) | Value::Dynamic(_) => false
+            };
+            if is_same {
+                *slot = Value::Bytes(value);
+                return
+            }
+        }
+    }
+}
+
+/// the setmap for reading, even if a panic poisoned its lock.
+///
+/// It's never left half changed, every change is a single insert or remove,
+/// so the worker goes on serving what's in it rather than refusing everything.
+fn read_setmap(setmap: &SetMap) -> RwLockReadGuard<'_, HashMap<Atom, Entry>> {
+    match setmap.read() {
+        Ok(setmap) => setmap,
+        Err(err) => err.into_inner()
+    }
+}
+
+/// the setmap for writing, even if a panic poisoned its lock, see `read_setmap`.
+fn write_setmap(setmap: &SetMap) -> RwLockWriteGuard<'_, HashMap<Atom, Entry>> {
+    match setmap.write() {
+        Ok(setmap) => setmap,
+        Err(err) => err.into_inner()
+    }
+}
+
+/// the error that closed the connection.
+fn connection_error(connection: &Connection) -> Error {
+    connection.has_error().err().unwrap_or(ConnError::Connection).into()
+}
+
+/// How far an INCR transfer of a stored value got, see `Clipboard::store_with_progress`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StoreProgress {
+    pub selection: Atom,
+    /// the window the value is sent to.
+    pub requestor: Window,
+    pub sent: usize,
+    pub total: usize
+}
+
+/// What came back from `Clipboard::load_status`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LoadOutcome {
+    Data(Vec<u8>),
+    /// the owner converted the selection to an empty value.
+    Empty,
+    /// the owner could not convert the selection to the target.
+    Refused,
+    /// nobody owns the selection.
+    NoOwner
+}
+
+impl LoadOutcome {
+    /// the data, or nothing if it's empty or refused, like `load` returns it.
+    fn into_data(self) -> Result<Vec<u8>, Error> {
+        match self {
+            LoadOutcome::Data(buff) => Ok(buff),
+            LoadOutcome::Empty | LoadOutcome::Refused => Ok(Vec::new()),
+            LoadOutcome::NoOwner => Err(Error::NoOwner)
+        }
+    }
+}
+
+/// Byte order of multi-byte values in a buffer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteOrder {
+    Little,
+    Big
+}
+
+impl ByteOrder {
+    /// byte order of this host.
+    pub fn native() -> ByteOrder {
+        if cfg!(target_endian = "big") { ByteOrder::Big }
+        else { ByteOrder::Little }
+    }
+}
+
+/// parse a buffer of 32-bit atoms stored in the given byte order.
+///
+/// The server swaps format 16 and 32 property values into the byte order of the
+/// requesting client, so anything returned by `load` for such a target is already
+/// in `ByteOrder::native()`. Other orders only matter for atom lists that were
+/// carried around as plain bytes, e.g. received from another machine.
+pub fn parse_atoms_with_order(buff: &[u8], order: ByteOrder) -> Vec<Atom> {
+    buff.chunks_exact(4)
+        .map(|b| {
+            let b = [b[0], b[1], b[2], b[3]];
+            match order {
+                ByteOrder::Little => Atom::from_le_bytes(b),
+                ByteOrder::Big => Atom::from_be_bytes(b)
+            }
+        })
+        .collect()
+}
+
+/// hash targets and their data, independent of the order they were given in.
+///
+/// Derived targets count with their source, and deferred values are
+/// not known yet, so only their target counts.
+fn fingerprint(targets: &[(Atom, Value)]) -> u64 {
+    let mut sorted = targets.iter().collect::<Vec<_>>();
+    sorted.sort_by_key(|&&(target, _)| target);
+
+    let mut hasher = DefaultHasher::new();
+    for &(target, ref value) in sorted {
+        target.hash(&mut hasher);
+        match *value {
+            Value::Bytes(ref value) | Value::Typed { data: ref value, .. } => Some(value.as_slice()),
+            Value::Shared(ref value) => Some((**value).as_ref()),
+            Value::Derived(ref source, _) => Some(source.as_slice()),
+            Value::Deferred(_) | Value::Dynamic(_) => None
+        }.hash(&mut hasher);
+    }
+    hasher.finish()
+}
 
 #[derive(Clone, Debug)]
 pub struct Atoms {
@@ -24,22 +350,129 @@ pub struct Atoms {
     pub targets: Atom,
     pub string: Atom,
     pub utf8_string: Atom,
-    pub incr: Atom
+    pub incr: Atom,
+    pub timestamp: Atom,
+    pub multiple: Atom,
+    pub length: Atom,
+    pub clipboard_manager: Atom,
+    pub save_targets: Atom,
+    pub delete: Atom,
+    pub null: Atom,
+    /// interned up front through `ClipboardBuilder::extra_atoms`.
+    extra: HashMap<String, Atom>
+}
+
+impl Atoms {
+    /// intern the atoms we use along with `extra`, in a single round trip.
+    fn new_with_extra(connection: &Connection, extra: &[&str]) -> Result<Atoms, Error> {
+        let mut names = vec![
+            "CLIPBOARD", "THIS_CLIPBOARD_OUT", "TARGETS", "UTF8_STRING",
+            "INCR", "TIMESTAMP", "MULTIPLE", "LENGTH",
+            "CLIPBOARD_MANAGER", "SAVE_TARGETS", "DELETE", "NULL"
+        ];
+        names.extend_from_slice(extra);
+        let interned = get_atoms(connection, &names, false)?;
+
+        Ok(Atoms {
+            primary: xcb::ATOM_PRIMARY,
+            clipboard: interned[0],
+            property: interned[1],
+            targets: interned[2],
+            string: xcb::ATOM_STRING,
+            utf8_string: interned[3],
+            incr: interned[4],
+            timestamp: interned[5],
+            multiple: interned[6],
+            length: interned[7],
+            clipboard_manager: interned[8],
+            save_targets: interned[9],
+            delete: interned[10],
+            null: interned[11],
+            extra: extra.iter()
+                .map(|&name| name.to_owned())
+                .zip(interned[12..].iter().cloned())
+                .collect()
+        })
+    }
+
+    /// an atom interned up front, `None` unless it was asked for with
+    /// `ClipboardBuilder::extra_atoms`.
+    pub fn extra(&self, name: &str) -> Option<Atom> {
+        self.extra.get(name).cloned()
+    }
+
+    /// `name`, or the number for atoms we don't know, for logging.
+    fn label(&self, atom: Atom) -> Cow<'static, str> {
+        match self.name(atom) {
+            Some(name) => Cow::Borrowed(name),
+            None => Cow::Owned(format!("ATOM({})", atom))
+        }
+    }
+
+    /// name of a well-known atom, without asking the server.
+    fn name(&self, atom: Atom) -> Option<&'static str> {
+        let known = [
+            (self.primary, "PRIMARY"),
+            (self.clipboard, "CLIPBOARD"),
+            (self.property, "THIS_CLIPBOARD_OUT"),
+            (self.targets, "TARGETS"),
+            (self.string, "STRING"),
+            (self.utf8_string, "UTF8_STRING"),
+            (self.incr, "INCR"),
+            (self.timestamp, "TIMESTAMP"),
+            (self.multiple, "MULTIPLE"),
+            (self.length, "LENGTH"),
+            (self.clipboard_manager, "CLIPBOARD_MANAGER"),
+            (self.save_targets, "SAVE_TARGETS"),
+            (self.delete, "DELETE"),
+            (self.null, "NULL"),
+            (xcb::ATOM_SECONDARY, "SECONDARY"),
+            (xcb::ATOM_ATOM, "ATOM"),
+            (xcb::ATOM_INTEGER, "INTEGER"),
+            (xcb::ATOM_CARDINAL, "CARDINAL"),
+            (xcb::ATOM_NONE, "None")
+        ];
+        known.iter()
+            .find(|&&(known, _)| known == atom)
+            .map(|&(_, name)| name)
+    }
 }
 
 /// X11 Clipboard
 pub struct Clipboard {
     pub getter: Context,
+    /// the context stored selections are served from, until the worker reconnects,
+    /// see `ClipboardBuilder::reconnect_on_loss`.
     pub setter: Arc<Context>,
+    serving: Serving,
     setmap: SetMap,
-    send: Sender<Atom>
+    policy: SharedPolicy,
+    released: Released,
+    /// cleared once the worker thread stops.
+    alive: Arc<AtomicBool>,
+    send: Sender<Atom>,
+    default_timeout: Option<Duration>,
+    builder: ClipboardBuilder
+}
+
+/// The screen a `Context` was opened on, see `Context::screen_info`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ScreenInfo {
+    pub root: Window,
+    pub width_px: u16,
+    pub height_px: u16,
+    pub root_visual: xcb::Visualid
 }
 
 pub struct Context {
     pub connection: Connection,
     pub screen: i32,
     pub window: Window,
-    pub atoms: Atoms
+    pub atoms: Atoms,
+    properties: PropertyPool,
+    /// how long a load parks between polls, doubling from the first to the second.
+    poll_backoff: (Duration, Duration),
+    metrics: Option<Arc<dyn MetricsSink>>
 }
 
 #[inline]
@@ -50,8 +483,36 @@ fn get_atom(connection: &Connection, name: &str) -> Result<Atom, Error> {
         .map_err(Into::into)
 }
 
+/// intern all of `names` in one go: every request is sent before the first reply is
+/// waited for, so it takes a single round trip rather than one per name.
+fn get_atoms(connection: &Connection, names: &[&str], only_if_exists: bool) -> Result<Vec<Atom>, Error> {
+    let cookies = names.iter()
+        .map(|name| xcb::intern_atom(connection, only_if_exists, name))
+        .collect::<Vec<_>>();
+    cookies.into_iter()
+        .map(|cookie| cookie.get_reply()
+            .map(|reply| reply.atom())
+            .map_err(Into::into))
+        .collect()
+}
+
 impl Context {
     pub fn new(displayname: Option<&str>) -> Result<Self, Error> {
+        Context::new_with_extra_atoms(displayname, &[])
+    }
+
+    /// like `new`, interning `extra` along with the atoms we use, see `Atoms::extra`.
+    pub fn new_with_extra_atoms(displayname: Option<&str>, extra: &[&str]) -> Result<Self, Error> {
+        Context::open(displayname, extra, 0)
+    }
+
+    /// like `new`, selecting `event_mask`, e.g. `xcb::EVENT_MASK_FOCUS_CHANGE`, on our
+    /// window on top of `STRUCTURE_NOTIFY` and `PROPERTY_CHANGE`, which loads need.
+    pub fn new_with_event_mask(displayname: Option<&str>, event_mask: u32) -> Result<Self, Error> {
+        Context::open(displayname, &[], event_mask)
+    }
+
+    pub(crate) fn open(displayname: Option<&str>, extra: &[&str], event_mask: u32) -> Result<Self, Error> {
         let (connection, screen) = Connection::connect(displayname)?;
         let window = connection.generate_id();
 
@@ -68,87 +529,183 @@ impl Context {
                 screen.root_visual(),
                 &[(
                     xcb::CW_EVENT_MASK,
-                    xcb::EVENT_MASK_STRUCTURE_NOTIFY | xcb::EVENT_MASK_PROPERTY_CHANGE
+                    event_mask | xcb::EVENT_MASK_STRUCTURE_NOTIFY | xcb::EVENT_MASK_PROPERTY_CHANGE
                 )]
             );
             connection.flush();
         }
 
-        macro_rules! intern_atom {
-            ( $name:expr ) => {
-                get_atom(&connection, $name)?
-            }
-        }
-
-        let atoms = Atoms {
-            primary: xcb::ATOM_PRIMARY,
-            clipboard: intern_atom!("CLIPBOARD"),
-            property: intern_atom!("THIS_CLIPBOARD_OUT"),
-            targets: intern_atom!("TARGETS"),
-            string: xcb::ATOM_STRING,
-            utf8_string: intern_atom!("UTF8_STRING"),
-            incr: intern_atom!("INCR")
-        };
+        let atoms = Atoms::new_with_extra(&connection, extra)?;
 
-        Ok(Context { connection, screen, window, atoms })
+        let poll_backoff = (Duration::from_millis(MIN_POLL_DURATION), Duration::from_millis(POLL_DURATION));
+        Ok(Context { connection, screen, window, atoms, properties: PropertyPool::default(), poll_backoff, metrics: None })
     }
 
     pub fn get_atom(&self, name: &str) -> Result<Atom, Error> {
         get_atom(&self.connection, name)
     }
-}
 
+    /// intern several atoms with a single round trip, in the order given.
+    ///
+    /// With `only_if_exists`, names the server hasn't seen yet come back as `xcb::ATOM_NONE`.
+    pub fn get_atoms(&self, names: &[&str], only_if_exists: bool) -> Result<Vec<Atom>, Error> {
+        get_atoms(&self.connection, names, only_if_exists)
+    }
 
-impl Clipboard {
-    /// Create Clipboard.
-    pub fn new() -> Result<Self, Error> {
-        let getter = Context::new(None)?;
-        let setter = Arc::new(Context::new(None)?);
-        let setter2 = Arc::clone(&setter);
-        let setmap = Arc::new(RwLock::new(HashMap::new()));
-        let setmap2 = Arc::clone(&setmap);
+    /// root window, size and visual of our screen.
+    pub fn screen_info(&self) -> Result<ScreenInfo, Error> {
+        let screen = self.connection.get_setup().roots()
+            .nth(self.screen as usize)
+            .ok_or(Error::XcbConn(ConnError::ClosedInvalidScreen))?;
+        Ok(ScreenInfo {
+            root: screen.root(),
+            width_px: screen.width_in_pixels(),
+            height_px: screen.height_in_pixels(),
+            root_visual: screen.root_visual()
+        })
+    }
 
-        let (sender, receiver) = channel();
-        let max_length = setter.connection.get_maximum_request_length() as usize * 4;
-        thread::spawn(move || run::run(&setter2, &setmap2, max_length, &receiver));
+    /// file descriptor of the connection, to wait for events in an outside event loop
+    /// such as `mio` or `epoll`, then read them with `poll_for_event`.
+    ///
+    /// It stays valid as long as the `Context` does, don't close it.
+    pub fn raw_fd(&self) -> RawFd {
+        self.connection.as_raw_fd()
+    }
 
-        Ok(Clipboard { getter, setter, setmap, send: sender })
+    /// read part of a property on our window, e.g. to preview a large value,
+    /// without deleting it.
+    ///
+    /// `offset` and `length` count 4-byte units, as the protocol does. Returns the
+    /// type, `xcb::ATOM_NONE` if the property doesn't exist, and the bytes read.
+    pub fn get_property_range(&self, property: Atom, offset: u32, length: u32)
+        -> Result<(Atom, Vec<u8>), Error>
+    {
+        let reply = xcb::get_property(
+            &self.connection, false, self.window,
+            property, xcb::ATOM_ANY, offset, length
+        ).get_reply()?;
+        Ok((reply.type_(), reply.value::<u8>().to_vec()))
     }
 
-    fn process_event<T>(&self, buff: &mut Vec<u8>, selection: Atom, target: Atom, property: Atom, timeout: T, use_xfixes: bool, xfixes_event_base: u8)
-        -> Result<(), Error>
-        where T: Into<Option<Duration>>
+    /// let loads poll after `min` at first, and twice as long each time nothing
+    /// arrived up to `max`. By default they start at 2ms and back off up to 50ms,
+    /// starting over whenever an event arrives.
+    pub fn set_poll_backoff(&mut self, min: Duration, max: Duration) {
+        self.poll_backoff = (min, cmp::max(min, max));
+    }
+
+    /// report loads, and transfers served from this context, to `sink`.
+    pub fn set_metrics(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics = Some(sink);
+    }
+
+    /// borrow a property atom from this context's pool, see `PropertyPool`.
+    pub fn checkout_property(&self) -> Result<PooledProperty<'_>, Error> {
+        self.properties.checkout(&self.connection)
+    }
+
+    /// delete the properties this crate uses from our window, e.g. left there by
+    /// a load that never finished.
+    pub fn cleanup_properties(&self) -> Result<(), Error> {
+        let properties = xcb::list_properties(&self.connection, self.window)
+            .get_reply()?
+            .atoms()
+            .to_vec();
+        let cookies = properties.iter()
+            .map(|&atom| xcb::get_atom_name(&self.connection, atom))
+            .collect::<Vec<_>>();
+
+        for (&atom, cookie) in properties.iter().zip(cookies) {
+            let name = cookie.get_reply()?;
+            let name = name.name();
+            if atom == self.atoms.timestamp
+                || name == "THIS_CLIPBOARD_OUT"
+                || name.starts_with("THIS_CLIPBOARD_OUT_")
+            {
+                xcb::delete_property(&self.connection, self.window, atom);
+            }
+        }
+        self.connection.flush();
+        Ok(())
+    }
+
+    /// current server time, read off the `PropertyNotify` of an empty append.
+    fn server_time(&self) -> Result<xcb::Timestamp, Error> {
+        xcb::change_property(
+            &self.connection, xcb::PROP_MODE_APPEND as u8,
+            self.window, self.atoms.timestamp, xcb::ATOM_INTEGER, 32,
+            &[0u32; 0]
+        );
+        self.connection.flush();
+
+        loop {
+            let event = match self.connection.wait_for_event() {
+                Some(event) => event,
+                None => return Err(connection_error(&self.connection))
+            };
+            if event.response_type() & !0x80 != xcb::PROPERTY_NOTIFY { continue };
+
+            let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+            if event.window() == self.window && event.atom() == self.atoms.timestamp {
+                return Ok(event.time());
+            }
+        }
+    }
+
+    /// Returns the type the data came as, or `None` if the owner refused the conversion.
+    ///
+    /// Unless `options.any_type` is set, data of a type `target` isn't expected to
+    /// come as is an `Error::UnexpectedType`.
+    fn process_event<S: Sink>(&self, window: Window, buff: &mut S, selection: Atom, target: Atom, property: Atom, options: LoadOptions)
+        -> Result<Option<Atom>, Error>
     {
+        let LoadOptions { deadline, control, any_type, xfixes_event_base } = options;
+        let use_xfixes = xfixes_event_base.is_some();
         let mut is_incr = false;
-        let timeout = timeout.into();
-        let start_time =
-            if timeout.is_some() { Some(Instant::now()) }
-            else { None };
+        let mut type_ = xcb::ATOM_NONE;
+        let start_time = Instant::now();
+        let (min_poll, max_poll) = self.poll_backoff;
+        let mut poll = min_poll;
 
         loop {
-            if timeout.into_iter()
-                .zip(start_time)
-                .next()
-                .map(|(timeout, time)| (Instant::now() - time) >= timeout)
-                .unwrap_or(false)
-            {
-                return Err(Error::Timeout);
+            if deadline.has_passed() {
+                let elapsed = start_time.elapsed();
+                debug!(
+                    "timed out after {} ms waiting for {} of {}",
+                    elapsed.as_millis(), self.atoms.label(target), self.atoms.label(selection)
+                );
+                return Err(Error::Timeout(TimeoutInfo { selection, target, elapsed }));
+            }
+
+            if let Some(control) = control {
+                if control.is_cancelled() {
+                    return Err(Error::Cancelled);
+                }
+                control.set_progress(buff.len());
             }
 
             let event = match use_xfixes {
                 true => {
-                    match self.getter.connection.wait_for_event() {
+                    match self.connection.wait_for_event() {
                         Some(event) => event,
-                        None => {
-                            continue
-                        }
+                        // the connection is gone, waiting again would return at once, forever.
+                        None => return Err(connection_error(&self.connection))
                     }
                 },
                 false => {
-                    match self.getter.connection.poll_for_event() {
-                        Some(event) => event,
+                    match self.connection.poll_for_event() {
+                        Some(event) => {
+                            poll = min_poll;
+                            event
+                        },
+                        None if self.connection.has_error().is_err() =>
+                            return Err(connection_error(&self.connection)),
                         None => {
-                            thread::park_timeout(Duration::from_millis(POLL_DURATION));
+                            // Don't sleep past the deadline.
+                            let left = deadline.remaining();
+                            thread::park_timeout(left.map_or(poll, |left| cmp::min(poll, left)));
+                            poll = cmp::min(poll * 2, max_poll);
                             continue
                         }
                     }
@@ -157,12 +714,12 @@ impl Clipboard {
 
             let r = event.response_type();
 
-            if use_xfixes && r == (xfixes_event_base + xcb::xfixes::SELECTION_NOTIFY) {
+            if xfixes_event_base.is_some_and(|base| r == base + xcb::xfixes::SELECTION_NOTIFY) {
                 let event = unsafe { xcb::cast_event::<xcb::xfixes::SelectionNotifyEvent>(&event) };
-                xcb::convert_selection(&self.getter.connection, self.getter.window,
+                xcb::convert_selection(&self.connection, window,
                                        selection, target, property,
                                        event.timestamp());
-                self.getter.connection.flush();
+                self.connection.flush();
                 continue;
             }
 
@@ -174,179 +731,1462 @@ impl Clipboard {
                     // Note that setting the property argument to None indicates that the
                     // conversion requested could not be made.
                     if event.property() == xcb::ATOM_NONE {
-                        break;
+                        return Ok(None);
                     }
 
                     let reply =
                         xcb::get_property(
-                            &self.getter.connection, false, self.getter.window,
+                            &self.connection, false, window,
                             event.property(), xcb::ATOM_ANY, buff.len() as u32, ::std::u32::MAX // FIXME reasonable buffer size
                         )
                         .get_reply()?;
 
-                    if reply.type_() == self.getter.atoms.incr {
-                        if let Some(&size) = reply.value::<i32>().get(0) {
-                            buff.reserve(size as usize);
+                    if reply.type_() == self.atoms.incr {
+                        debug!("receiving {} of {} through INCR", self.atoms.label(target), self.atoms.label(selection));
+                        // Read as bytes, `value::<u32>` asserts the length is a multiple
+                        // of 4, which trailing data below needn't be.
+                        let value = reply.value::<u8>();
+                        if value.len() >= 4 {
+                            // The hint is a lower bound only, and it takes all 32 bits:
+                            // past 2GB it must not be read as negative.
+                            let size = u32::from_ne_bytes([value[0], value[1], value[2], value[3]]);
+                            buff.reserve(cmp::min(size as usize, INCR_RESERVE_LIMIT));
+                            if let Some(control) = control {
+                                control.set_size_hint(size as usize);
+                            }
+                            // Some owners put the first chunk right after the size hint,
+                            // instead of leaving the INCR property value at just the hint.
+                            buff.write(&value[4..])?;
                         }
-                        xcb::delete_property(&self.getter.connection, self.getter.window, property);
-                        self.getter.connection.flush();
+                        xcb::delete_property(&self.connection, window, property);
+                        self.connection.flush();
                         is_incr = true;
                         continue
-                    } else if reply.type_() != target {
+                    } else if !any_type && !self.is_expected_type(target, reply.type_()) {
                         return Err(Error::UnexpectedType(reply.type_()));
+                    } else if reply.bytes_after() != 0 {
+                        // A single read covers `u32::MAX` 4-byte units, anything bigger
+                        // has to come through INCR.
+                        return Err(Error::TooLargeForDirectTransfer);
                     }
 
-                    buff.extend_from_slice(reply.value());
+                    type_ = reply.type_();
+                    buff.write(reply.value())?;
+                    if let Some(control) = control {
+                        control.set_size_hint(buff.len());
+                    }
                     break
                 },
                 xcb::PROPERTY_NOTIFY if is_incr => {
                     let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
-                    if event.state() != xcb::PROPERTY_NEW_VALUE as u8 { continue };
-
-                    let length =
-                        xcb::get_property(
-                            &self.getter.connection, false, self.getter.window,
-                            property, xcb::ATOM_ANY, 0, 0
-                        )
-                        .get_reply()
-                        .map(|reply| reply.bytes_after())?;
+                    if event.atom() != property || event.state() != xcb::PROPERTY_NEW_VALUE as u8 { continue };
 
+                    // Read everything in one go, anything the owner appended since the
+                    // notify included. The property is only deleted once it's read in full.
                     let reply =
                         xcb::get_property(
-                            &self.getter.connection, true, self.getter.window,
-                            property, xcb::ATOM_ANY, 0, length
+                            &self.connection, true, window,
+                            property, xcb::ATOM_ANY, 0, u32::MAX
                         )
                         .get_reply()?;
 
-                    if reply.type_() != target { continue };
+                    // Already read along with an earlier chunk.
+                    if reply.type_() == xcb::ATOM_NONE { continue };
 
-                    if reply.value_len() != 0 {
-                        buff.extend_from_slice(reply.value());
-                    } else {
+                    // The zero-length terminator ends the transfer whatever its type,
+                    // not every owner bothers to give it the target type.
+                    if reply.value_len() == 0 {
+                        debug!("received {} bytes through INCR", buff.len());
                         break
                     }
+
+                    if !any_type && !self.is_expected_type(target, reply.type_()) { continue };
+                    type_ = reply.type_();
+                    buff.write(reply.value())?;
+                    trace!("received {} bytes so far", buff.len());
                 },
                 _ => ()
             }
         }
-        Ok(())
+
+        if let Some(control) = control {
+            control.set_progress(buff.len());
+        }
+        if let Some(ref metrics) = self.metrics {
+            metrics.on_load(selection, buff.len(), start_time.elapsed());
+        }
+        Ok(Some(type_))
     }
 
-    /// load value.
-    pub fn load<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+    /// Data targets come back typed as themselves or as a list of `ATOM`,
+    /// like `TARGETS` does, `TIMESTAMP` is an `INTEGER` and `DELETE` a `NULL`.
+    #[inline]
+    fn is_expected_type(&self, target: Atom, type_: Atom) -> bool {
+        type_ == target
+            || type_ == xcb::ATOM_ATOM
+            || (target == self.atoms.timestamp && type_ == xcb::ATOM_INTEGER)
+            || (target == self.atoms.length && (type_ == xcb::ATOM_CARDINAL || type_ == xcb::ATOM_INTEGER))
+            || (target == self.atoms.delete && type_ == self.atoms.null)
+    }
+
+    fn load<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
         -> Result<Vec<u8>, Error>
-        where T: Into<Option<Duration>>
+        where T: Into<Deadline>
     {
-        let mut buff = Vec::new();
-        let timeout = timeout.into();
+        let time = self.server_time()?;
+        self.load_at(selection, target, property, time, timeout)
+    }
 
-        xcb::convert_selection(
-            &self.getter.connection, self.getter.window,
-            selection, target, property,
-            xcb::CURRENT_TIME
-                // FIXME ^
-                // Clients should not use CurrentTime for the time argument of a ConvertSelection request.
-                // Instead, they should use the timestamp of the event that caused the request to be made.
-        );
-        self.getter.connection.flush();
+    fn load_at<T>(&self, selection: Atom, target: Atom, property: Atom, time: xcb::Timestamp, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Deadline>
+    {
+        self.convert(selection, target, property, time, timeout, None)?.into_data()
+    }
 
-        self.process_event(&mut buff, selection, target, property, timeout, false, 0)?;
-        xcb::delete_property(&self.getter.connection, self.getter.window, property);
-        self.getter.connection.flush();
-        Ok(buff)
+    fn load_status<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<LoadOutcome, Error>
+        where T: Into<Deadline>
+    {
+        let time = self.server_time()?;
+        self.convert(selection, target, property, time, timeout, None)
     }
 
-    /// wait for a new value and load it
-    pub fn load_wait(&self, selection: Atom, target: Atom, property: Atom)
-        -> Result<Vec<u8>, Error>
+    /// ask the owner to convert the selection as of `time`.
+    ///
+    /// Clients should not use `CURRENT_TIME` here, but the timestamp of the event
+    /// that caused the request, or at least a fresh `server_time`.
+    fn convert<T>(&self, selection: Atom, target: Atom, property: Atom, time: xcb::Timestamp, timeout: T, control: Option<&LoadControl>)
+        -> Result<LoadOutcome, Error>
+        where T: Into<Deadline>
     {
+        let deadline = timeout.into();
+        if let Some(outcome) = self.request_conversion(selection, target, property, time)? {
+            return Ok(outcome);
+        }
+
         let mut buff = Vec::new();
+        let converted = self.process_event(self.window, &mut buff, selection, target, property, LoadOptions {
+            deadline, control, ..LoadOptions::default()
+        })?;
+        Ok(self.finish_conversion(property, buff, converted).1)
+    }
 
-        let screen = &self.getter.connection.get_setup().roots()
-            .nth(self.getter.screen as usize)
-            .ok_or(Error::XcbConn(ConnError::ClosedInvalidScreen))?;
+    /// `convert` accepting data of any type, and returning the type it came as
+    /// along with it, `xcb::ATOM_NONE` without data.
+    fn convert_typed<T>(&self, selection: Atom, target: Atom, property: Atom, time: xcb::Timestamp, timeout: T)
+        -> Result<(Atom, LoadOutcome), Error>
+        where T: Into<Deadline>
+    {
+        let deadline = timeout.into();
+        if let Some(outcome) = self.request_conversion(selection, target, property, time)? {
+            return Ok((xcb::ATOM_NONE, outcome));
+        }
 
-        let xfixes = xcb::query_extension(
-            &self.getter.connection, "XFIXES").get_reply()?;
-        assert!(xfixes.present());
-        xcb::xfixes::query_version(&self.getter.connection, 5, 0);
-        // Clear selection sources...
-        xcb::xfixes::select_selection_input(
-            &self.getter.connection, screen.root(), self.getter.atoms.primary, 0);
-        xcb::xfixes::select_selection_input(
-            &self.getter.connection, screen.root(), self.getter.atoms.clipboard, 0);
-        // ...and set the one requested now
-        xcb::xfixes::select_selection_input(
-            &self.getter.connection, screen.root(), selection,
-            xcb::xfixes::SELECTION_EVENT_MASK_SET_SELECTION_OWNER |
-            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_CLIENT_CLOSE |
-            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_WINDOW_DESTROY);
-        self.getter.connection.flush();
+        let mut buff = Vec::new();
+        let converted = self.process_event(self.window, &mut buff, selection, target, property, LoadOptions {
+            deadline, any_type: true, ..LoadOptions::default()
+        })?;
+        Ok(self.finish_conversion(property, buff, converted))
+    }
 
-        self.process_event(&mut buff, selection, target, property, None, true, xfixes.first_event())?;
-        xcb::delete_property(&self.getter.connection, self.getter.window, property);
-        self.getter.connection.flush();
-        Ok(buff)
+    /// send `ConvertSelection`, or return the outcome right away if there's nobody to ask.
+    fn request_conversion(&self, selection: Atom, target: Atom, property: Atom, time: xcb::Timestamp)
+        -> Result<Option<LoadOutcome>, Error>
+    {
+        self.request_conversion_on(self.window, selection, target, property, time)
     }
 
-    /// store value.
-    pub fn store<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T)
-        -> Result<(), Error>
+    fn request_conversion_on(&self, window: Window, selection: Atom, target: Atom, property: Atom, time: xcb::Timestamp)
+        -> Result<Option<LoadOutcome>, Error>
     {
-        self.send.send(selection)?;
-        self.setmap
-            .write()
-            .map_err(|_| Error::Lock)?
-            .insert(selection, (target, value.into()));
+        let owner = xcb::get_selection_owner(&self.connection, selection)
+            .get_reply()?
+            .owner();
+        if owner == xcb::NONE {
+            return Ok(Some(LoadOutcome::NoOwner));
+        }
+        // Nobody would answer, the window is busy waiting on itself.
+        if owner == window {
+            return Err(Error::SelfConversionUnsupported);
+        }
 
-        xcb::set_selection_owner(
-            &self.setter.connection,
-            self.setter.window, selection,
-            xcb::CURRENT_TIME
+        debug!(
+            "converting {} of {} owned by window {}",
+            self.atoms.label(target), self.atoms.label(selection), owner
+        );
+        xcb::convert_selection(
+            &self.connection, window,
+            selection, target, property,
+            time
         );
+        self.connection.flush();
+        Ok(None)
+    }
 
-        self.setter.connection.flush();
+    fn finish_conversion(&self, property: Atom, buff: Vec<u8>, converted: Option<Atom>) -> (Atom, LoadOutcome) {
+        self.finish_conversion_on(self.window, property, buff, converted)
+    }
 
-        if xcb::get_selection_owner(&self.setter.connection, selection)
-            .get_reply()
-            .map(|reply| reply.owner() == self.setter.window)
-            .unwrap_or(false)
-        {
-            Ok(())
-        } else {
-            Err(Error::Owner)
+    fn finish_conversion_on(&self, window: Window, property: Atom, buff: Vec<u8>, converted: Option<Atom>) -> (Atom, LoadOutcome) {
+        xcb::delete_property(&self.connection, window, property);
+        self.connection.flush();
+        match converted {
+            Some(type_) if buff.is_empty() => (type_, LoadOutcome::Empty),
+            Some(type_) => (type_, LoadOutcome::Data(buff)),
+            None => (xcb::ATOM_NONE, LoadOutcome::Refused)
         }
     }
 
-    pub fn store_multiple<T: Into<Vec<u8>>>(&self, selection: Atom, targets: HashMap<Atom, T>)
-    -> Result<(), Error>
-{
-    self.send.send(selection)?;
+    fn load_target_atoms<T>(&self, selection: Atom, timeout: T)
+        -> Result<Vec<Atom>, Error>
+        where T: Into<Deadline>
     {
-        let mut hash = self.setmap
-            .write()
-            .map_err(|_| Error::Lock)?;
-        for (target, value) in targets {
-            hash.insert(selection, (target, value.into()));
-        }
+        self.load_atoms(selection, self.atoms.targets, timeout)
     }
 
-    xcb::set_selection_owner(
-        &self.setter.connection,
-        self.setter.window, selection,
-        xcb::CURRENT_TIME
-    );
+    fn load_atoms<T>(&self, selection: Atom, target: Atom, timeout: T)
+        -> Result<Vec<Atom>, Error>
+        where T: Into<Deadline>
+    {
+        let buff = self.load(selection, target, self.atoms.property, timeout)?;
+        Ok(parse_atoms_with_order(&buff, ByteOrder::native()))
+    }
+}
 
-    self.setter.connection.flush();
 
-    if xcb::get_selection_owner(&self.setter.connection, selection)
-        .get_reply()
-        .map(|reply| reply.owner() == self.setter.window)
-        .unwrap_or(false)
-    {
-        Ok(())
-    } else {
-        Err(Error::Owner)
+impl Drop for Clipboard {
+    fn drop(&mut self) {
+        if let Some(timeout) = self.builder.persist_timeout() {
+            // without a manager, or one that doesn't answer, the data just goes.
+            let _ = self.hand_to_manager(timeout);
+        }
+
+        // give up what we own, so no one waits on answers from a worker that's gone.
+        // The lock isn't held meanwhile, the worker takes it to forget the selections.
+        let selections = read_setmap(&self.setmap).iter()
+            .map(|(&selection, entry)| (selection, entry.acquired))
+            .collect::<Vec<_>>();
+        let setter = self.serving();
+        for (selection, acquired) in selections {
+            run::disown(&setter, selection, acquired);
+        }
+
+        // close the channel first, so the worker finds it closed once woken.
+        drop(mem::replace(&mut self.send, mpsc::channel().0));
+        run::wakeup(&setter);
     }
 }
+
+impl Clipboard {
+    /// ask the clipboard manager to save what we stored in `CLIPBOARD`, the ICCCM
+    /// `SAVE_TARGETS` handshake, and wait for it to be done.
+    ///
+    /// The worker keeps serving the manager meanwhile. Returns whether it saved anything.
+    fn hand_to_manager(&self, timeout: Duration) -> Result<bool, Error> {
+        let getter = &self.getter;
+        let atoms = &getter.atoms;
+        if !read_setmap(&self.setmap).contains_key(&atoms.clipboard) {
+            return Ok(false);
+        }
+        let manager = xcb::get_selection_owner(&getter.connection, atoms.clipboard_manager)
+            .get_reply()?
+            .owner();
+        if manager == xcb::NONE {
+            return Ok(false);
+        }
+
+        // an empty property asks for every target to be saved.
+        xcb::delete_property(&getter.connection, getter.window, atoms.property);
+        xcb::convert_selection(
+            &getter.connection, getter.window,
+            atoms.clipboard_manager, atoms.save_targets, atoms.property,
+            xcb::CURRENT_TIME
+        );
+        getter.connection.flush();
+
+        let deadline = Deadline::after(Some(timeout));
+        while !deadline.has_passed() {
+            let event = match getter.connection.poll_for_event() {
+                Some(event) => event,
+                None => {
+                    let poll = Duration::from_millis(POLL_DURATION);
+                    thread::park_timeout(deadline.remaining().map_or(poll, |left| cmp::min(poll, left)));
+                    continue
+                }
+            };
+            if event.response_type() & !0x80 != xcb::SELECTION_NOTIFY { continue };
+
+            let event = unsafe { xcb::cast_event::<xcb::SelectionNotifyEvent>(&event) };
+            if event.selection() == atoms.clipboard_manager {
+                xcb::delete_property(&getter.connection, getter.window, atoms.property);
+                getter.connection.flush();
+                return Ok(event.property() != xcb::ATOM_NONE);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Create Clipboard.
+    pub fn new() -> Result<Self, Error> {
+        ClipboardBuilder::new().build()
+    }
+
+    /// Create Clipboard on `displayname`, e.g. `":1"`, rather than `$DISPLAY`.
+    pub fn with_display(displayname: &str) -> Result<Self, Error> {
+        ClipboardBuilder::new().display(displayname).build()
+    }
+
+    /// replace both connections with fresh ones, e.g. after the X server restarted.
+    ///
+    /// The previous worker thread exits. With `auto_restore_on_reconnect`, everything
+    /// stored before is published again, and the first failure to do so is returned.
+    pub fn reconnect(&mut self) -> Result<(), Error> {
+        let mut fresh = self.builder.clone().build()?;
+        fresh.default_timeout = self.default_timeout;
+        fresh.set_requestor_policy(self.requestor_policy());
+
+        let old = mem::replace(self, fresh);
+        let entries = mem::take(&mut *write_setmap(&old.setmap));
+        drop(old);
+
+        if !self.builder.restores_on_reconnect() {
+            return Ok(());
+        }
+
+        let mut result = Ok(());
+        for (selection, entry) in entries {
+            let restored = self.store_entry(selection, entry);
+            if result.is_ok() {
+                result = restored;
+            }
+        }
+        result
+    }
+
+    /// Targets that describe the selection, or act on it, rather than hold its data.
+    #[inline]
+    fn is_meta_target(&self, target: Atom) -> bool {
+        let atoms = &self.getter.atoms;
+        target == atoms.targets || target == atoms.timestamp || target == atoms.multiple
+            || target == atoms.length || target == atoms.delete || target == atoms.save_targets
+    }
+
+    /// load value.
+    ///
+    /// Fails with `Error::NoOwner` right away if nobody owns the selection.
+    pub fn load<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.getter.load(selection, target, property, timeout.into())
+    }
+
+    /// load value, reporting progress to `control` and stopping if it's cancelled.
+    ///
+    /// Cancellation is noticed between INCR chunks, or while waiting for the owner.
+    pub fn load_controlled<T>(&self, selection: Atom, target: Atom, property: Atom, control: &LoadControl, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let time = self.getter.server_time()?;
+        self.getter.convert(selection, target, property, time, timeout.into(), Some(control))?.into_data()
+    }
+
+    /// load value, converting it onto `window` rather than `getter.window`.
+    ///
+    /// The owner sends `SelectionNotify` to whoever created `window`, so create it on
+    /// `getter.connection`. INCR transfers also need `PROPERTY_CHANGE` in its event mask,
+    /// that's up to the caller.
+    pub fn load_on_window<T>(&self, window: Window, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let getter = &self.getter;
+        let time = getter.server_time()?;
+        if let Some(outcome) = getter.request_conversion_on(window, selection, target, property, time)? {
+            return outcome.into_data();
+        }
+
+        let mut buff = Vec::new();
+        let converted = getter.process_event(window, &mut buff, selection, target, property, LoadOptions {
+            deadline: Deadline::after(timeout.into()), ..LoadOptions::default()
+        })?;
+        getter.finish_conversion_on(window, property, buff, converted).1.into_data()
+    }
+
+    /// load value, giving up at `deadline` rather than after a timeout.
+    ///
+    /// Loads made one after another with the same deadline share one budget,
+    /// where each would get the whole of a timeout.
+    pub fn load_until<T>(&self, selection: Atom, target: Atom, property: Atom, deadline: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Deadline>
+    {
+        self.getter.load(selection, target, property, deadline)
+    }
+
+    /// load value along with the type it came as, which may differ from `target`,
+    /// e.g. `UTF8_STRING` for `TEXT`.
+    ///
+    /// Data of any type is accepted. Without data the type is `xcb::ATOM_NONE`.
+    pub fn load_typed<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<(Atom, Vec<u8>), Error>
+        where T: Into<Option<Duration>>
+    {
+        let time = self.getter.server_time()?;
+        let (type_, outcome) = self.getter.convert_typed(selection, target, property, time, timeout.into())?;
+        Ok((type_, outcome.into_data()?))
+    }
+
+    /// load value if the owner answers right away, `None` if it doesn't.
+    ///
+    /// Also `None` without an owner or when the conversion is refused,
+    /// meant for event loops that poll every frame and can't block.
+    pub fn try_load(&self, selection: Atom, target: Atom, property: Atom)
+        -> Result<Option<Vec<u8>>, Error>
+    {
+        match self.load_status(selection, target, property, Duration::from_millis(TRY_LOAD_WINDOW)) {
+            Ok(LoadOutcome::Data(buff)) => Ok(Some(buff)),
+            Ok(LoadOutcome::Empty) => Ok(Some(Vec::new())),
+            Ok(LoadOutcome::Refused) | Ok(LoadOutcome::NoOwner) | Err(Error::Timeout(_)) => Ok(None),
+            Err(err) => Err(err)
+        }
+    }
+
+    /// load value, asking for the selection as of the server timestamp `time`.
+    ///
+    /// Pass the timestamp of the event that made you paste, so an owner that took
+    /// the selection after it isn't asked. `xcb::CURRENT_TIME` is still accepted,
+    /// but discouraged. `load` uses a fresh server time instead.
+    pub fn load_with_timestamp<T>(&self, selection: Atom, target: Atom, property: Atom, time: xcb::Timestamp, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.getter.load_at(selection, target, property, time, timeout.into())
+    }
+
+    /// load value, telling apart the ways of getting nothing back.
+    ///
+    /// A timeout is still reported as `Error::Timeout`.
+    pub fn load_status<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<LoadOutcome, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.getter.load_status(selection, target, property, timeout.into())
+    }
+
+    /// load text, preferring `UTF8_STRING` and falling back to Latin-1 `STRING`.
+    ///
+    /// Invalid UTF-8 is replaced rather than reported.
+    fn load_text(&self, selection: Atom, deadline: Deadline) -> Result<String, Error> {
+        let atoms = &self.getter.atoms;
+        match self.getter.load_status(selection, atoms.utf8_string, atoms.property, deadline)? {
+            LoadOutcome::Data(buff) => return Ok(String::from_utf8_lossy(&buff).into_owned()),
+            LoadOutcome::Empty | LoadOutcome::NoOwner => return Ok(String::new()),
+            LoadOutcome::Refused => ()
+        }
+
+        let buff = self.getter.load(selection, atoms.string, atoms.property, deadline)?;
+        Ok(buff.iter().map(|&b| b as char).collect())
+    }
+
+    /// load text with `\r\n` and lone `\r` line endings turned into `\n`.
+    pub fn get_text_normalized<T>(&self, selection: Atom, timeout: T)
+        -> Result<String, Error>
+        where T: Into<Option<Duration>>
+    {
+        let text = self.load_text(selection, Deadline::after(timeout.into()))?;
+        Ok(text.replace("\r\n", "\n").replace('\r', "\n"))
+    }
+
+    /// load text from `CLIPBOARD`, else from `PRIMARY`, and if neither is owned
+    /// from `CUT_BUFFER0` on the root window, which some old programs still set instead.
+    pub fn get_text_with_cutbuffer_fallback<T>(&self, timeout: T)
+        -> Result<String, Error>
+        where T: Into<Option<Duration>>
+    {
+        let deadline = Deadline::after(timeout.into());
+        for &selection in &[self.getter.atoms.clipboard, self.getter.atoms.primary] {
+            let owner = xcb::get_selection_owner(&self.getter.connection, selection)
+                .get_reply()?
+                .owner();
+            if owner != xcb::NONE {
+                return self.load_text(selection, deadline);
+            }
+        }
+
+        let root = self.getter.screen_info()?.root;
+        let reply = xcb::get_property(
+            &self.getter.connection, false, root,
+            xcb::ATOM_CUT_BUFFER0, xcb::ATOM_STRING, 0, u32::MAX
+        ).get_reply()?;
+        Ok(reply.value::<u8>().iter().map(|&b| b as char).collect())
+    }
+
+    /// set the timeout used by `load_default`, `None` waits forever.
+    pub fn set_default_timeout(&mut self, timeout: Option<Duration>) {
+        self.default_timeout = timeout;
+    }
+
+    /// load value, waiting at most the default timeout.
+    pub fn load_default(&self, selection: Atom, target: Atom, property: Atom)
+        -> Result<Vec<u8>, Error>
+    {
+        self.load(selection, target, property, self.default_timeout)
+    }
+
+    /// load value through a property checked out of the getter's pool.
+    pub fn load_pooled<T>(&self, selection: Atom, target: Atom, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let property = self.getter.checkout_property()?;
+        self.load(selection, target, property.atom(), timeout)
+    }
+
+    /// wait for a new value and load it
+    pub fn load_wait(&self, selection: Atom, target: Atom, property: Atom)
+        -> Result<Vec<u8>, Error>
+    {
+        let mut buff = Vec::new();
+
+        let screen = &self.getter.connection.get_setup().roots()
+            .nth(self.getter.screen as usize)
+            .ok_or(Error::XcbConn(ConnError::ClosedInvalidScreen))?;
+
+        let xfixes = xcb::query_extension(
+            &self.getter.connection, "XFIXES").get_reply()?;
+        assert!(xfixes.present());
+        xcb::xfixes::query_version(&self.getter.connection, 5, 0);
+        // Clear selection sources...
+        xcb::xfixes::select_selection_input(
+            &self.getter.connection, screen.root(), self.getter.atoms.primary, 0);
+        xcb::xfixes::select_selection_input(
+            &self.getter.connection, screen.root(), self.getter.atoms.clipboard, 0);
+        // ...and set the one requested now
+        xcb::xfixes::select_selection_input(
+            &self.getter.connection, screen.root(), selection,
+            xcb::xfixes::SELECTION_EVENT_MASK_SET_SELECTION_OWNER |
+            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_CLIENT_CLOSE |
+            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_WINDOW_DESTROY);
+        self.getter.connection.flush();
+
+        self.getter.process_event(self.getter.window, &mut buff, selection, target, property, LoadOptions {
+            xfixes_event_base: Some(xfixes.first_event()), ..LoadOptions::default()
+        })?;
+        xcb::delete_property(&self.getter.connection, self.getter.window, property);
+        self.getter.connection.flush();
+        Ok(buff)
+    }
+
+    /// wait for any of `selections` to get a new value for `target` and load it,
+    /// e.g. to follow both `PRIMARY` and `CLIPBOARD` at once.
+    ///
+    /// Returns the selection that changed along with its value. New owners that
+    /// refuse `target` are waited past.
+    pub fn load_wait_any(&self, selections: &[Atom], target: Atom, property: Atom)
+        -> Result<(Atom, Vec<u8>), Error>
+    {
+        let getter = &self.getter;
+        let root = getter.screen_info()?.root;
+
+        let xfixes = xcb::query_extension(&getter.connection, "XFIXES").get_reply()?;
+        if !xfixes.present() {
+            return Err(Error::XcbConn(ConnError::ClosedExtNotSupported));
+        }
+        xcb::xfixes::query_version(&getter.connection, 5, 0).get_reply()?;
+
+        let select = |mask| {
+            for &selection in selections {
+                xcb::xfixes::select_selection_input(&getter.connection, root, selection, mask);
+            }
+            getter.connection.flush();
+        };
+        select(xcb::xfixes::SELECTION_EVENT_MASK_SET_SELECTION_OWNER);
+        let notify_event = xfixes.first_event() + xcb::xfixes::SELECTION_NOTIFY;
+
+        let result = loop {
+            let event = match getter.connection.wait_for_event() {
+                Some(event) => event,
+                None => break Err(connection_error(&getter.connection))
+            };
+            if event.response_type() & !0x80 != notify_event { continue };
+
+            let event = unsafe { xcb::cast_event::<xcb::xfixes::SelectionNotifyEvent>(&event) };
+            let selection = event.selection();
+            if !selections.contains(&selection) || event.owner() == xcb::NONE { continue };
+
+            match getter.convert(selection, target, property, event.timestamp(), Deadline::never(), None) {
+                Ok(LoadOutcome::Data(buff)) => break Ok((selection, buff)),
+                Ok(LoadOutcome::Empty) => break Ok((selection, Vec::new())),
+                Ok(LoadOutcome::Refused) | Ok(LoadOutcome::NoOwner) => (),
+                Err(err) => break Err(err)
+            }
+        };
+        select(0);
+        result
+    }
+
+    /// like `load_wait`, but a clear of `selection` while waiting ends the wait:
+    /// with `Error::Cleared` if `fail_on_clear` is set, or an empty value if not.
+    ///
+    /// Our window never owns anything, so it's told about the clear by XFIXES
+    /// rather than `SelectionClear`.
+    pub fn load_wait_or_cleared(&self, selection: Atom, target: Atom, property: Atom, fail_on_clear: bool)
+        -> Result<Vec<u8>, Error>
+    {
+        let getter = &self.getter;
+        let root = getter.screen_info()?.root;
+
+        let xfixes = xcb::query_extension(&getter.connection, "XFIXES").get_reply()?;
+        if !xfixes.present() {
+            return Err(Error::XcbConn(ConnError::ClosedExtNotSupported));
+        }
+        xcb::xfixes::query_version(&getter.connection, 5, 0).get_reply()?;
+
+        let select = |mask| {
+            xcb::xfixes::select_selection_input(&getter.connection, root, selection, mask);
+            getter.connection.flush();
+        };
+        select(
+            xcb::xfixes::SELECTION_EVENT_MASK_SET_SELECTION_OWNER |
+            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_CLIENT_CLOSE |
+            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_WINDOW_DESTROY);
+        let notify_event = xfixes.first_event() + xcb::xfixes::SELECTION_NOTIFY;
+        let cleared = || match fail_on_clear {
+            true => Err(Error::Cleared),
+            false => Ok(Vec::new())
+        };
+
+        let result = loop {
+            let event = match getter.connection.wait_for_event() {
+                Some(event) => event,
+                None => break Err(connection_error(&getter.connection))
+            };
+            if event.response_type() & !0x80 != notify_event { continue };
+
+            let event = unsafe { xcb::cast_event::<xcb::xfixes::SelectionNotifyEvent>(&event) };
+            if event.selection() != selection { continue };
+            if event.owner() == xcb::NONE {
+                break cleared();
+            }
+
+            break match getter.convert(selection, target, property, event.timestamp(), Deadline::never(), None) {
+                Ok(LoadOutcome::NoOwner) => cleared(),
+                Ok(outcome) => outcome.into_data(),
+                Err(err) => Err(err)
+            };
+        };
+
+        select(0);
+        result
+    }
+
+    /// store value.
+    pub fn store<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T)
+        -> Result<(), Error>
+    {
+        self.store_entry(selection, Entry::new(vec![(target, Value::Bytes(value.into()))]))
+    }
+
+    /// store a list of atoms, served as 32-bit data of type `ATOM`.
+    pub fn store_atoms(&self, selection: Atom, target: Atom, atoms: Vec<Atom>)
+        -> Result<(), Error>
+    {
+        let data = atoms.iter().flat_map(|atom| atom.to_ne_bytes().to_vec()).collect();
+        let value = Value::Typed { type_: xcb::ATOM_ATOM, format: 32, data };
+        self.store_entry(selection, Entry::new(vec![(target, value)]))
+    }
+
+    /// store text as `UTF8_STRING`, and as Latin-1 `STRING` too when it can be,
+    /// for older clients such as xterm.
+    pub fn store_text(&self, selection: Atom, text: &str) -> Result<(), Error> {
+        let atoms = &self.setter.atoms;
+        let mut targets = vec![(atoms.utf8_string, Value::Bytes(text.as_bytes().to_vec()))];
+        if text.chars().all(|c| (c as u32) < 0x100) {
+            let latin1 = text.chars().map(|c| c as u8).collect();
+            targets.push((atoms.string, Value::Bytes(latin1)));
+        }
+        self.store_entry(selection, Entry::new(targets))
+    }
+
+    /// store an image under its mime type as target, e.g. `image/png`.
+    pub fn store_image(&self, selection: Atom, mime: &str, bytes: Vec<u8>) -> Result<(), Error> {
+        let target = self.setter.get_atom(mime)?;
+        self.store(selection, target, bytes)
+    }
+
+    /// load an image of the mime type, e.g. `image/png`, empty if the owner has none.
+    pub fn load_image<T>(&self, selection: Atom, mime: &str, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let target = self.getter.get_atom(mime)?;
+        self.load(selection, target, self.getter.atoms.property, timeout)
+    }
+
+    /// store value without copying it, it's served straight from `value`.
+    ///
+    /// Meant for buffers that aren't a `Vec`, or are shared with the rest of the app.
+    pub fn store_shared<T>(&self, selection: Atom, target: Atom, value: Arc<T>)
+        -> Result<(), Error>
+        where T: AsRef<[u8]> + Send + Sync + 'static
+    {
+        self.store_entry(selection, Entry::new(vec![(target, Value::Shared(value))]))
+    }
+
+    /// store one value served under several targets, each computed from it on request.
+    ///
+    /// The source is kept once instead of a copy per target, `derive` runs on the
+    /// worker thread every time its target is requested.
+    pub fn store_derived(&self, selection: Atom, source: Vec<u8>, targets: Vec<(Atom, Derive)>)
+        -> Result<(), Error>
+    {
+        let source = Arc::new(source);
+        let targets = targets.into_iter()
+            .map(|(target, derive)| (target, Value::Derived(Arc::clone(&source), derive)))
+            .collect();
+        self.store_entry(selection, Entry::new(targets))
+    }
+
+    /// store a target whose value is computed when someone asks for it.
+    ///
+    /// `provider` is called on the worker thread for each request and must return
+    /// quickly; the value is sent through the returned channel whenever it is ready,
+    /// typically from a thread of the caller's. Other requests are served meanwhile.
+    /// The first value received is kept and served to later requests as is.
+    ///
+    /// Every pending request parks a helper thread on the channel until the value
+    /// arrives. Dropping the sender without sending refuses the request.
+    pub fn store_deferred<F>(&self, selection: Atom, target: Atom, provider: F)
+        -> Result<(), Error>
+        where F: Fn() -> Receiver<Vec<u8>> + Send + Sync + 'static
+    {
+        self.store_entry(selection, Entry::new(vec![(target, Value::Deferred(Arc::new(provider)))]))
+    }
+
+    /// store a target whose value `provider` computes for each request, from the
+    /// requestor window and the target, e.g. to give untrusted windows less.
+    ///
+    /// It's called on the worker thread and must return quickly. Returning `None`
+    /// refuses the request.
+    pub fn store_dynamic<F>(&self, selection: Atom, target: Atom, provider: F)
+        -> Result<(), Error>
+        where F: FnMut(Window, Atom) -> Option<Vec<u8>> + Send + 'static
+    {
+        self.store_entry(selection, Entry::new(vec![(target, Value::Dynamic(Mutex::new(Box::new(provider))))]))
+    }
+
+    /// store value that can be pasted only once.
+    ///
+    /// After the first request for `target` has been served, the data is dropped and
+    /// the selection relinquished. This is inherently racy: a requestor that fails
+    /// halfway through an INCR transfer still uses up the single paste.
+    pub fn store_ephemeral<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T)
+        -> Result<(), Error>
+    {
+        let entry = Entry { ephemeral: true, ..Entry::new(vec![(target, Value::Bytes(value.into()))]) };
+        self.store_entry(selection, entry)
+    }
+
+    /// store value and call `on_request` with the requestor window each time it's pasted.
+    ///
+    /// The callback runs on the worker thread, so it must return quickly and not store
+    /// anything itself. It's dropped once the selection is lost or stored over.
+    pub fn store_with_callback<T, F>(&self, selection: Atom, target: Atom, value: T, on_request: F)
+        -> Result<(), Error>
+        where T: Into<Vec<u8>>, F: FnMut(Window) + Send + 'static
+    {
+        let entry = Entry {
+            on_request: Some(Mutex::new(Box::new(on_request))),
+            ..Entry::new(vec![(target, Value::Bytes(value.into()))])
+        };
+        self.store_entry(selection, entry)
+    }
+
+    /// store value and send a `StoreProgress` down `progress` for each chunk
+    /// written while it's pasted through INCR.
+    ///
+    /// Values small enough to go in one request aren't reported. Sending stops
+    /// quietly once the receiver is dropped, the transfer goes on.
+    pub fn store_with_progress<T>(&self, selection: Atom, target: Atom, value: T, progress: Sender<StoreProgress>)
+        -> Result<(), Error>
+        where T: Into<Vec<u8>>
+    {
+        let entry = Entry {
+            progress: Some(Mutex::new(progress)),
+            ..Entry::new(vec![(target, Value::Bytes(value.into()))])
+        };
+        self.store_entry(selection, entry)
+    }
+
+    /// store value that is cleared after `ttl`, unless something else was stored meanwhile.
+    ///
+    /// The worker does the clearing, waking up for it when it's due.
+    pub fn store_expiring<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T, ttl: Duration)
+        -> Result<(), Error>
+    {
+        let entry = Entry {
+            expires: Some(Instant::now() + ttl),
+            ..Entry::new(vec![(target, Value::Bytes(value.into()))])
+        };
+        self.store_entry(selection, entry)?;
+
+        // the worker may be blocked with no expiry in sight, let it see this one.
+        run::wakeup(&self.serving());
+        Ok(())
+    }
+
+    /// store value and block until someone pastes it.
+    ///
+    /// Gives up with `Error::Timeout` or `Error::Cancelled`, relinquishing the selection
+    /// so it isn't left to a program that is about to exit, or with
+    /// `Error::OwnerChanged` if another client took the selection first.
+    pub fn store_and_wait<T, D>(&self, selection: Atom, target: Atom, value: T, timeout: D, cancel: &CancelToken)
+        -> Result<(), Error>
+        where T: Into<Vec<u8>>, D: Into<Option<Duration>>
+    {
+        let start = Instant::now();
+        let deadline = timeout.into().map(|timeout| start + timeout);
+        let entry = Entry::new(vec![(target, Value::Bytes(value.into()))]);
+        let pastes = Arc::clone(&entry.pastes);
+        self.store_entry(selection, entry)?;
+
+        let mut count = pastes.count.lock().map_err(|_| Error::Lock)?;
+        loop {
+            if *count > 0 {
+                return Ok(());
+            }
+
+            let error = if cancel.is_cancelled() {
+                Some(Error::Cancelled)
+            } else if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                Some(Error::Timeout(TimeoutInfo { selection, target, elapsed: start.elapsed() }))
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                drop(count);
+                self.clear_sync(selection)?;
+                return Err(error);
+            }
+
+            let ours = read_setmap(&self.setmap)
+                .get(&selection)
+                .is_some_and(|entry| Arc::ptr_eq(&entry.pastes, &pastes));
+            if !ours {
+                return Err(Error::OwnerChanged);
+            }
+
+            // wake up now and then to notice cancellation.
+            count = pastes.condvar.wait_timeout(count, Duration::from_millis(POLL_DURATION))
+                .map_err(|_| Error::Lock)?
+                .0;
+        }
+    }
+
+    /// store value and block until it has been pasted once, like `xclip` does
+    /// before exiting, see `store_and_wait`.
+    pub fn store_blocking<T, D>(&self, selection: Atom, target: Atom, value: T, timeout: D)
+        -> Result<(), Error>
+        where T: Into<Vec<u8>>, D: Into<Option<Duration>>
+    {
+        self.store_and_wait(selection, target, value, timeout, &CancelToken::new())
+    }
+
+    /// store value that is only served to `allowed_requestor`, everyone else is refused.
+    pub fn store_targeted<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T, allowed_requestor: Window)
+        -> Result<(), Error>
+    {
+        let entry = Entry {
+            requestor: Some(allowed_requestor),
+            ..Entry::new(vec![(target, Value::Bytes(value.into()))])
+        };
+        self.store_entry(selection, entry)
+    }
+
+    /// store value that can be pasted only once, and only by `allowed_requestor`.
+    ///
+    /// See `store_ephemeral` and `store_targeted`.
+    pub fn store_targeted_ephemeral<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T, allowed_requestor: Window)
+        -> Result<(), Error>
+    {
+        let entry = Entry {
+            ephemeral: true,
+            requestor: Some(allowed_requestor),
+            ..Entry::new(vec![(target, Value::Bytes(value.into()))])
+        };
+        self.store_entry(selection, entry)
+    }
+
+    /// store value, taking the selection over with the latest server time.
+    ///
+    /// This is intentionally aggressive: unlike `store`, it never leaves an owner that
+    /// grabbed the selection a moment ago in place, which is what clipboard managers
+    /// want and almost nobody else does. Use it sparingly.
+    pub fn store_force<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T)
+        -> Result<(), Error>
+    {
+        let time = self.getter.server_time()?;
+        self.store_entry_at(selection, Entry::new(vec![(target, Value::Bytes(value.into()))]), time)
+    }
+
+    fn store_entry(&self, selection: Atom, entry: Entry) -> Result<(), Error> {
+        self.store_entry_at(selection, entry, xcb::CURRENT_TIME)
+    }
+
+    fn store_entry_at(&self, selection: Atom, mut entry: Entry, time: xcb::Timestamp) -> Result<(), Error> {
+        // nobody would serve it.
+        if !self.is_alive() {
+            return Err(Error::WorkerDead);
+        }
+
+        // Already ours, so the new entry is served as is. This is asked of the server
+        // rather than the setmap, which only learns of a loss once the worker gets to it.
+        let owned = time == xcb::CURRENT_TIME && self.is_owner(selection)?;
+        let previous = match owned {
+            true => read_setmap(&self.setmap)
+                .get(&selection)
+                .map(|entry| entry.acquired),
+            false => None
+        };
+        // `CURRENT_TIME` can't be answered for `TIMESTAMP`, the worker stamps the entry
+        // with the time just before taking the selection, see `run::stamp`.
+        entry.acquired = previous.unwrap_or(time);
+        let unstamped = entry.acquired == xcb::CURRENT_TIME;
+
+        if let Some(ref metrics) = self.getter.metrics {
+            let bytes = entry.targets.iter()
+                .filter_map(|(_, value)| value.len())
+                .sum();
+            metrics.on_store(selection, bytes);
+        }
+
+        self.send.send(selection)?;
+        write_setmap(&self.setmap).insert(selection, entry);
+
+        let setter = self.serving();
+        if unstamped {
+            // Only the worker reads the setter's events, the `PropertyNotify` of this
+            // reaches it ahead of any request made once the selection is ours.
+            xcb::change_property(
+                &setter.connection, xcb::PROP_MODE_APPEND as u8,
+                setter.window, setter.atoms.timestamp, xcb::ATOM_INTEGER, 32,
+                &[0u32; 0]
+            );
+        }
+        if owned {
+            setter.connection.flush();
+            debug!("replaced the value of {}, already owned", self.atom_label(selection));
+            return Ok(());
+        }
+
+        xcb::set_selection_owner(
+            &setter.connection,
+            setter.window, selection,
+            time
+        );
+
+        setter.connection.flush();
+        self.check_owner(selection)?;
+        debug!("took ownership of {}", self.atom_label(selection));
+        Ok(())
+    }
+
+    /// choose which requestor windows are served, everyone by default.
+    ///
+    /// Requests from other windows are refused, whatever the selection.
+    pub fn set_requestor_policy(&self, policy: RequestorPolicy) {
+        match self.policy.write() {
+            Ok(mut current) => *current = policy,
+            Err(err) => *err.into_inner() = policy
+        }
+    }
+
+    /// the policy set with `set_requestor_policy`.
+    pub fn requestor_policy(&self) -> RequestorPolicy {
+        match self.policy.read() {
+            Ok(policy) => policy.clone(),
+            Err(err) => err.into_inner().clone()
+        }
+    }
+
+    /// block until the selection we own is taken by someone else or given up.
+    ///
+    /// Returns right away if we don't own it.
+    pub fn wait_for_ownership_loss<T>(&self, selection: Atom, timeout: T)
+        -> Result<(), Error>
+        where T: Into<Option<Duration>>
+    {
+        let start = Instant::now();
+        let deadline = timeout.into().map(|timeout| start + timeout);
+        let (ref lock, ref condvar) = *self.released;
+        let mut guard = lock.lock().map_err(|_| Error::Lock)?;
+
+        while read_setmap(&self.setmap).contains_key(&selection) {
+            guard = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        let elapsed = now - start;
+                        return Err(Error::Timeout(TimeoutInfo { selection, target: xcb::ATOM_NONE, elapsed }));
+                    }
+                    condvar.wait_timeout(guard, deadline - now).map_err(|_| Error::Lock)?.0
+                },
+                None => condvar.wait(guard).map_err(|_| Error::Lock)?
+            };
+        }
+        Ok(())
+    }
+
+    /// readable label for an atom, for logging.
+    ///
+    /// Well-known atoms are named without a round-trip, others come out as `ATOM(<id>)`.
+    pub fn atom_label(&self, atom: Atom) -> Cow<'static, str> {
+        self.getter.atoms.label(atom)
+    }
+
+    /// give up a selection we own and drop what was stored for it.
+    pub fn clear(&self, selection: Atom) -> Result<(), Error> {
+        self.send.send(selection)?;
+        let acquired = read_setmap(&self.setmap).get(&selection).map(|entry| entry.acquired);
+        run::forget(&self.setmap, &self.released, selection);
+
+        if let Some(acquired) = acquired {
+            let setter = self.serving();
+            run::disown(&setter, selection, acquired);
+            setter.connection.flush();
+        }
+        Ok(())
+    }
+
+    /// the largest value a requestor gets in a single reply. Larger values are
+    /// sent through INCR, in chunks, without anything to do on our side.
+    pub fn max_inline_size(&self) -> usize {
+        run::max_inline_size(&self.serving())
+    }
+
+    /// whether the worker thread serving stored selections is still running.
+    ///
+    /// It stops when the setter's connection is lost, after which storing fails with
+    /// `Error::WorkerDead` until `reconnect`.
+    pub fn is_alive(&self) -> bool {
+        self.alive.load(Ordering::SeqCst)
+    }
+
+    /// `clear` every selection we own, e.g. both `PRIMARY` and `CLIPBOARD`.
+    pub fn clear_all(&self) -> Result<(), Error> {
+        let selections = read_setmap(&self.setmap)
+            .keys()
+            .cloned()
+            .collect::<Vec<_>>();
+        for selection in selections {
+            self.clear(selection)?;
+        }
+        Ok(())
+    }
+
+    /// `clear`, then wait for the server to have processed it,
+    /// so other clients see the selection unowned once this returns.
+    pub fn clear_sync(&self, selection: Atom) -> Result<(), Error> {
+        self.clear(selection)?;
+        xcb::get_selection_owner(&self.serving().connection, selection).get_reply()?;
+        Ok(())
+    }
+
+    /// store `size` pseudo-random bytes, load them back and return how long it took.
+    ///
+    /// It goes through the server both ways, INCR included for large sizes, so it
+    /// tells how fast the clipboard is on this connection. The load waits at most
+    /// the default timeout.
+    pub fn benchmark_roundtrip(&self, size: usize) -> Result<Duration, Error> {
+        let selection = self.setter.get_atom("X11_CLIPBOARD_BENCHMARK")?;
+        let target = self.setter.get_atom("X11_CLIPBOARD_BENCHMARK_DATA")?;
+
+        let mut hasher = DefaultHasher::new();
+        Instant::now().hash(&mut hasher);
+        let mut state = hasher.finish() | 1;
+        let data = (0..size)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect::<Vec<u8>>();
+
+        let start = Instant::now();
+        self.store(selection, target, data.as_slice())?;
+        let result = self.load_default(selection, target, self.getter.atoms.property);
+        let elapsed = start.elapsed();
+        self.clear(selection)?;
+
+        if result? != data {
+            return Err(Error::BenchmarkMismatch);
+        }
+        Ok(elapsed)
+    }
+
+    /// store text in `PRIMARY`, what's selected right now and pasted with
+    /// the middle button, see `store_text`.
+    pub fn store_primary(&self, text: &str) -> Result<(), Error> {
+        self.store_text(self.setter.atoms.primary, text)
+    }
+
+    /// load text from `PRIMARY`, see `load_string`.
+    pub fn load_primary<T>(&self, timeout: T) -> Result<String, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.load_string(self.getter.atoms.primary, self.getter.atoms.property, timeout)
+    }
+
+    /// store text in `CLIPBOARD`, what's copied explicitly and pasted with
+    /// Ctrl+V, see `store_text`.
+    pub fn store_clipboard(&self, text: &str) -> Result<(), Error> {
+        self.store_text(self.setter.atoms.clipboard, text)
+    }
+
+    /// load text from `CLIPBOARD`, see `load_string`.
+    pub fn load_clipboard<T>(&self, timeout: T) -> Result<String, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.load_string(self.getter.atoms.clipboard, self.getter.atoms.property, timeout)
+    }
+
+    /// give up `PRIMARY`, waiting until the server has it unowned.
+    ///
+    /// By convention `PRIMARY` holds what's selected right now, so call this
+    /// when the widget owning it loses focus or its selection is cleared.
+    pub fn relinquish_primary(&self) -> Result<(), Error> {
+        self.clear_sync(self.setter.atoms.primary)
+    }
+
+    /// fingerprint of what was last stored for the selection, while we still own it.
+    ///
+    /// It is computed once on store, so comparing fingerprints tells a real content
+    /// change from the same data being stored again.
+    pub fn content_fingerprint(&self, selection: Atom) -> Option<u64> {
+        read_setmap(&self.setmap)
+            .get(&selection)
+            .map(|entry| entry.fingerprint)
+    }
+
+    /// the selections we currently own, as far as the worker knows, without asking the server.
+    pub fn owned_selections(&self) -> Result<Vec<Atom>, Error> {
+        Ok(read_setmap(&self.setmap)
+            .keys()
+            .cloned()
+            .collect())
+    }
+
+    /// the targets stored for the selection, empty unless we own it.
+    pub fn owned_targets(&self, selection: Atom) -> Result<Vec<Atom>, Error> {
+        Ok(read_setmap(&self.setmap)
+            .get(&selection)
+            .map(|entry| entry.targets.iter().map(|&(target, _)| target).collect())
+            .unwrap_or_default())
+    }
+
+    /// how many times what was last stored for the selection has been pasted.
+    ///
+    /// Only data requests count, not `TARGETS`, and a new store starts over from zero.
+    pub fn paste_count(&self, selection: Atom) -> usize {
+        read_setmap(&self.setmap)
+            .get(&selection)
+            .map(|entry| Arc::clone(&entry.pastes))
+            .and_then(|pastes| pastes.count.lock().ok().map(|count| *count))
+            .unwrap_or(0)
+    }
+
+    fn is_owner(&self, selection: Atom) -> Result<bool, Error> {
+        let setter = self.serving();
+        let owner = xcb::get_selection_owner(&setter.connection, selection)
+            .get_reply()?
+            .owner();
+        Ok(owner == setter.window)
+    }
+
+    /// the context the worker serves from, `setter` unless it has reconnected since.
+    fn serving(&self) -> Arc<Context> {
+        match self.serving.read() {
+            Ok(serving) => Arc::clone(&serving),
+            Err(_) => Arc::clone(&self.setter)
+        }
+    }
+
+    /// confirm the setter window really became the owner.
+    ///
+    /// Another client may grab the selection in the same instant,
+    /// in which case the window that won is reported.
+    fn check_owner(&self, selection: Atom) -> Result<(), Error> {
+        let setter = self.serving();
+        let owner = xcb::get_selection_owner(&setter.connection, selection)
+            .get_reply()?
+            .owner();
+
+        if owner == setter.window {
+            Ok(())
+        } else {
+            Err(Error::Owner { actual: owner })
+        }
+    }
+
+    pub fn store_multiple<T: Into<Vec<u8>>>(&self, selection: Atom, targets: HashMap<Atom, T>)
+        -> Result<(), Error>
+    {
+        let targets = targets.into_iter()
+            .map(|(target, value)| (target, Value::Bytes(value.into())))
+            .collect();
+        self.store_entry(selection, Entry::new(targets))
+    }
+
+    /// add `target` to what's stored for the selection, or replace its value,
+    /// keeping the other targets, e.g. an HTML version computed after the text.
+    ///
+    /// Ownership is kept as is, an INCR transfer of the replaced value still going on
+    /// is dropped. Without a stored selection this is `store`.
+    pub fn add_target<T: Into<Vec<u8>>>(&self, selection: Atom, target: Atom, value: T)
+        -> Result<(), Error>
+    {
+        let value = value.into();
+        if self.is_owner(selection)? {
+            let mut setmap = write_setmap(&self.setmap);
+            if let Some(entry) = setmap.get_mut(&selection) {
+                match entry.targets.iter_mut().find(|&&mut (t, _)| t == target) {
+                    Some(slot) => {
+                        // an INCR transfer of the old value can't go on with the new one.
+                        self.send.send(selection)?;
+                        slot.1 = Value::Bytes(value);
+                    },
+                    None => entry.targets.push((target, Value::Bytes(value)))
+                }
+                entry.fingerprint = fingerprint(&entry.targets);
+                return Ok(());
+            }
+        }
+        self.store(selection, target, value)
+    }
+
+    /// drop `target` from what's stored for the selection, keeping the other targets.
+    ///
+    /// An INCR transfer of it still going on stops at the next chunk. Removing the
+    /// last target relinquishes the selection, like `clear`.
+    pub fn remove_target(&self, selection: Atom, target: Atom) -> Result<(), Error> {
+        let is_last = {
+            let mut setmap = write_setmap(&self.setmap);
+            let entry = match setmap.get_mut(&selection) {
+                Some(entry) => entry,
+                None => return Ok(())
+            };
+            entry.targets.retain(|&(t, _)| t != target);
+            entry.fingerprint = fingerprint(&entry.targets);
+            entry.targets.is_empty()
+        };
+
+        match is_last {
+            true => self.clear(selection),
+            false => Ok(())
+        }
+    }
+
+    /// load a target holding a list of atoms, see `store_atoms`.
+    pub fn load_atoms<T>(&self, selection: Atom, target: Atom, timeout: T)
+        -> Result<Vec<Atom>, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.getter.load_atoms(selection, target, timeout.into())
+    }
+
+    /// load the atoms listed by the owner's `TARGETS`.
+    pub fn load_target_atoms<T>(&self, selection: Atom, timeout: T)
+        -> Result<Vec<Atom>, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.getter.load_target_atoms(selection, timeout.into())
+    }
+
+    /// size of the owner's data as reported by the obsolete `LENGTH` target,
+    /// `None` if the owner doesn't answer it.
+    ///
+    /// It saves transferring the data, but an owner may well count it differently.
+    pub fn load_length<T>(&self, selection: Atom, timeout: T)
+        -> Result<Option<usize>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let atoms = &self.getter.atoms;
+        Ok(match self.load_status(selection, atoms.length, atoms.property, timeout)? {
+            LoadOutcome::Data(ref buff) if buff.len() == 4 =>
+                Some(u32::from_ne_bytes([buff[0], buff[1], buff[2], buff[3]]) as usize),
+            _ => None
+        })
+    }
+
+    /// whether two selections hold the same data for `target`, e.g. so that
+    /// syncing `PRIMARY` and `CLIPBOARD` doesn't copy back and forth.
+    ///
+    /// Data we stored ourselves is compared without going through the server.
+    pub fn selections_match<T>(&self, a: Atom, b: Atom, target: Atom, timeout: T)
+        -> Result<bool, Error>
+        where T: Into<Option<Duration>>
+    {
+        let deadline = Deadline::after(timeout.into());
+        let a = self.load_or_stored(a, target, deadline)?;
+        let b = self.load_or_stored(b, target, deadline)?;
+        Ok(a == b)
+    }
+
+    fn load_or_stored(&self, selection: Atom, target: Atom, deadline: Deadline)
+        -> Result<Vec<u8>, Error>
+    {
+        // The setmap may not have caught up with losing the selection yet.
+        if !self.is_owner(selection)? {
+            // two unowned selections hold the same nothing.
+            return match self.getter.load(selection, target, self.getter.atoms.property, deadline) {
+                Err(Error::NoOwner) => Ok(Vec::new()),
+                result => result
+            };
+        }
+
+        let stored = read_setmap(&self.setmap)
+            .get(&selection)
+            .and_then(|entry| entry.get(target))
+            .and_then(Value::bytes)
+            .map(<[u8]>::to_vec);
+        match stored {
+            Some(value) => Ok(value),
+            None => self.getter.load(selection, target, self.getter.atoms.property, deadline)
+        }
+    }
+
+    /// whether the owner supports the `MULTIPLE` target.
+    ///
+    /// ICCCM has every conforming owner support it whether listed or not, but plenty
+    /// of owners don't, so only owners listing it in `TARGETS` count. Owners that
+    /// refuse `TARGETS` give nothing to go by and are assumed to conform.
+    pub fn supports_multiple<T>(&self, selection: Atom, timeout: T)
+        -> Result<bool, Error>
+        where T: Into<Option<Duration>>
+    {
+        let atoms = &self.getter.atoms;
+        Ok(match self.load_status(selection, atoms.targets, atoms.property, timeout)? {
+            LoadOutcome::Data(buff) => parse_atoms_with_order(&buff, ByteOrder::native()).contains(&atoms.multiple),
+            LoadOutcome::Refused => true,
+            LoadOutcome::Empty | LoadOutcome::NoOwner => false
+        })
+    }
+
+    /// load the first of `targets` the owner offers, most preferred first,
+    /// e.g. `UTF8_STRING`, then `STRING`, then `TEXT`.
+    ///
+    /// Returns the target picked along with its data, `Error::NoMatchingTarget`
+    /// if none of them is offered or there's no owner.
+    pub fn load_any<T>(&self, selection: Atom, targets: &[Atom], property: Atom, timeout: T)
+        -> Result<(Atom, Vec<u8>), Error>
+        where T: Into<Option<Duration>>
+    {
+        let deadline = Deadline::after(timeout.into());
+        let offered = match self.getter.load_target_atoms(selection, deadline) {
+            Err(Error::NoOwner) => return Err(Error::NoMatchingTarget),
+            offered => offered?
+        };
+        let target = targets.iter()
+            .cloned()
+            .find(|target| offered.contains(target))
+            .ok_or(Error::NoMatchingTarget)?;
+        Ok((target, self.getter.load(selection, target, property, deadline)?))
+    }
+
+    /// load text as `UTF8_STRING`, or failing that as Latin-1 `STRING`.
+    ///
+    /// Unlike `get_text_normalized`, malformed UTF-8 is an `Error::InvalidUtf8`
+    /// rather than replaced. See `load_any` for owners offering neither.
+    pub fn load_string<T>(&self, selection: Atom, property: Atom, timeout: T)
+        -> Result<String, Error>
+        where T: Into<Option<Duration>>
+    {
+        let atoms = &self.getter.atoms;
+        let (target, buff) = self.load_any(selection, &[atoms.utf8_string, atoms.string], property, timeout)?;
+        if target == atoms.string {
+            return Ok(buff.iter().map(|&b| b as char).collect());
+        }
+        Ok(String::from_utf8(buff)?)
+    }
+
+    /// whether the owner lists `target` in `TARGETS`, without loading any data.
+    ///
+    /// `false` without an owner, or if it refuses `TARGETS`.
+    pub fn has_target<T>(&self, selection: Atom, target: Atom, timeout: T)
+        -> Result<bool, Error>
+        where T: Into<Option<Duration>>
+    {
+        let atoms = &self.getter.atoms;
+        Ok(match self.load_status(selection, atoms.targets, atoms.property, timeout)? {
+            LoadOutcome::Data(buff) => parse_atoms_with_order(&buff, ByteOrder::native()).contains(&target),
+            LoadOutcome::Empty | LoadOutcome::Refused | LoadOutcome::NoOwner => false
+        })
+    }
+
+    /// intern `name` only if it already exists, and return it if the owner offers it.
+    ///
+    /// An atom the server has never seen can't be offered by anyone,
+    /// so that case is answered without talking to the owner.
+    pub fn resolve_available_target<T>(&self, selection: Atom, name: &str, timeout: T)
+        -> Result<Option<Atom>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let atom = self.getter.get_atoms(&[name], true)?[0];
+        if atom == xcb::ATOM_NONE {
+            return Ok(None);
+        }
+
+        let targets = match self.load_target_atoms(selection, timeout) {
+            Err(Error::NoOwner) => return Ok(None),
+            targets => targets?
+        };
+        Ok(if targets.contains(&atom) { Some(atom) } else { None })
+    }
+
+    /// load every data target the owner offers.
+    ///
+    /// The owner and its `TIMESTAMP` are checked before and after,
+    /// and `Error::OwnerChanged` is returned if the selection changed hands meanwhile.
+    /// Targets that come back with a different type than requested are skipped.
+    pub fn snapshot<T>(&self, selection: Atom, timeout: T)
+        -> Result<Vec<(Atom, Vec<u8>)>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let deadline = Deadline::after(timeout.into());
+        let property = self.getter.atoms.property;
+
+        let before = self.owner_stamp(selection, deadline)?;
+
+        let mut snapshot = Vec::new();
+        for target in self.getter.load_target_atoms(selection, deadline)? {
+            if self.is_meta_target(target) { continue };
+
+            match self.getter.load(selection, target, property, deadline) {
+                Ok(value) => snapshot.push((target, value)),
+                Err(Error::UnexpectedType(_)) => (),
+                Err(err) => return Err(err)
+            }
+        }
+
+        if self.owner_stamp(selection, deadline)? != before {
+            return Err(Error::OwnerChanged);
+        }
+
+        Ok(snapshot)
+    }
+
+    fn owner_stamp(&self, selection: Atom, deadline: Deadline)
+        -> Result<(Window, Vec<u8>), Error>
+    {
+        let owner = xcb::get_selection_owner(&self.getter.connection, selection)
+            .get_reply()?
+            .owner();
+        let timestamp = self.getter.load(selection, self.getter.atoms.timestamp, self.getter.atoms.property, deadline)?;
+        Ok((owner, timestamp))
+    }
 }
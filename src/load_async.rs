@@ -0,0 +1,82 @@
+use std::thread;
+use std::pin::Pin;
+use std::future::Future;
+use std::time::Duration;
+use std::sync::{ Arc, Mutex };
+use std::task::{ Context as TaskContext, Poll, Waker };
+use xcb::Atom;
+use error::Error;
+use ::{ Clipboard, Context, LoadControl };
+
+/// Where the helper thread leaves the result for `LoadFuture`.
+#[derive(Default)]
+struct Shared {
+    result: Option<Result<Vec<u8>, Error>>,
+    waker: Option<Waker>
+}
+
+/// The value of a `Clipboard::load_async`, dropping it cancels the load.
+pub struct LoadFuture {
+    shared: Arc<Mutex<Shared>>,
+    control: Arc<LoadControl>
+}
+
+impl Future for LoadFuture {
+    type Output = Result<Vec<u8>, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut TaskContext) -> Poll<Self::Output> {
+        let mut shared = match self.shared.lock() {
+            Ok(shared) => shared,
+            Err(_) => return Poll::Ready(Err(Error::Lock))
+        };
+        match shared.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                shared.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl Drop for LoadFuture {
+    fn drop(&mut self) {
+        self.control.cancel();
+    }
+}
+
+impl Clipboard {
+    /// load value without blocking, for use from any async runtime.
+    ///
+    /// This trades a thread and a connection per call for not depending on a runtime:
+    /// the blocking load runs on a helper thread of its own.
+    pub fn load_async<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T) -> LoadFuture
+        where T: Into<Option<Duration>>
+    {
+        let timeout = timeout.into();
+        let shared = Arc::new(Mutex::new(Shared::default()));
+        let control = Arc::new(LoadControl::new());
+        let display = self.builder.display_name().map(str::to_owned);
+
+        {
+            let (shared, control) = (Arc::clone(&shared), Arc::clone(&control));
+            thread::spawn(move || {
+                let result = Context::new(display.as_deref())
+                    .and_then(|context| {
+                        let time = context.server_time()?;
+                        context.convert(selection, target, property, time, timeout, Some(&control))
+                    })
+                    .and_then(|outcome| outcome.into_data());
+
+                if let Ok(mut shared) = shared.lock() {
+                    shared.result = Some(result);
+                    if let Some(waker) = shared.waker.take() {
+                        waker.wake();
+                    }
+                }
+            });
+        }
+
+        LoadFuture { shared, control }
+    }
+}
@@ -0,0 +1,18 @@
+use std::time::Duration;
+use bytes::Bytes;
+use xcb::Atom;
+use error::Error;
+use ::Clipboard;
+
+impl Clipboard {
+    /// like `load`, but hands the value over as `bytes::Bytes`.
+    ///
+    /// INCR chunks land in the same buffer `load` uses, and it's given to
+    /// `Bytes` without a copy.
+    pub fn load_bytes<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<Bytes, Error>
+        where T: Into<Option<Duration>>
+    {
+        self.load(selection, target, property, timeout).map(Bytes::from)
+    }
+}
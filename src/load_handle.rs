@@ -0,0 +1,79 @@
+use std::panic;
+use std::thread::{ self, JoinHandle };
+use std::time::Duration;
+use std::sync::Arc;
+use xcb::Atom;
+use error::Error;
+use ::{ Clipboard, Context, LoadControl };
+
+/// A load running on a helper thread, see `Clipboard::load_cancellable`.
+///
+/// Dropping it without `wait` cancels the load.
+pub struct LoadHandle {
+    thread: Option<JoinHandle<Result<Vec<u8>, Error>>>,
+    control: Arc<LoadControl>
+}
+
+impl LoadHandle {
+    /// stop the load, `wait` returns `Error::Cancelled` right after.
+    ///
+    /// It can be called from any thread, e.g. when the user presses Escape.
+    pub fn cancel(&self) {
+        self.control.cancel();
+        // the helper sleeps between polls, wake it up so it notices at once.
+        if let Some(ref thread) = self.thread {
+            thread.thread().unpark();
+        }
+    }
+
+    /// progress of the load, shared with the helper thread.
+    pub fn control(&self) -> &LoadControl {
+        &self.control
+    }
+
+    /// block until the load finishes, is cancelled or times out.
+    pub fn wait(mut self) -> Result<Vec<u8>, Error> {
+        match self.thread.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result,
+            Some(Err(payload)) => panic::resume_unwind(payload),
+            None => Err(Error::Cancelled)
+        }
+    }
+}
+
+impl Drop for LoadHandle {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.cancel();
+        }
+    }
+}
+
+impl Clipboard {
+    /// load value on a helper thread, returning at once with a handle to cancel it.
+    ///
+    /// Unlike `load_controlled`, the caller doesn't block, so a GUI can abort a paste
+    /// that hangs on an owner never answering. Like `load_async`, this costs a
+    /// thread and a connection per call.
+    pub fn load_cancellable<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T) -> LoadHandle
+        where T: Into<Option<Duration>>
+    {
+        let timeout = timeout.into();
+        let control = Arc::new(LoadControl::new());
+        let display = self.builder.display_name().map(str::to_owned);
+
+        let thread = {
+            let control = Arc::clone(&control);
+            thread::spawn(move || {
+                Context::new(display.as_deref())
+                    .and_then(|context| {
+                        let time = context.server_time()?;
+                        context.convert(selection, target, property, time, timeout, Some(&control))
+                    })
+                    .and_then(|outcome| outcome.into_data())
+            })
+        };
+
+        LoadHandle { thread: Some(thread), control }
+    }
+}
@@ -0,0 +1,30 @@
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+use xcb::Atom;
+
+/// Receives transfer sizes and durations, see `ClipboardBuilder::metrics`.
+///
+/// Every method does nothing by default, so a sink only implements what it
+/// reports. Calls come from whichever thread finished the transfer, the worker
+/// included, so they should return quickly.
+pub trait MetricsSink: Send + Sync {
+    /// a value was stored, `bytes` leaves out values computed on request.
+    fn on_store(&self, _selection: Atom, _bytes: usize) {}
+
+    /// a load finished with `bytes` of data after `duration`.
+    fn on_load(&self, _selection: Atom, _bytes: usize, _duration: Duration) {}
+
+    /// an INCR transfer we served finished, sending `bytes` in `chunks`.
+    fn on_incr_transfer(&self, _selection: Atom, _bytes: usize, _chunks: usize) {}
+}
+
+/// The sink set on a builder, shared by every context built from it.
+#[derive(Clone)]
+pub(crate) struct Metrics(pub(crate) Arc<dyn MetricsSink>);
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Metrics(..)")
+    }
+}
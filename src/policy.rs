@@ -0,0 +1,46 @@
+use std::fmt;
+use std::sync::{ Arc, RwLock };
+use std::collections::HashSet;
+use xcb::Window;
+
+pub type SharedPolicy = Arc<RwLock<RequestorPolicy>>;
+
+/// Which requestor windows the setter serves, see `Clipboard::set_requestor_policy`.
+#[derive(Clone, Default)]
+pub enum RequestorPolicy {
+    #[default]
+    AllowAll,
+    DenyAll,
+    /// serve only these windows.
+    Allow(HashSet<Window>),
+    /// serve everyone but these windows.
+    Deny(HashSet<Window>),
+    /// serve the windows the predicate returns `true` for.
+    ///
+    /// It's called on the worker thread for every request and must return quickly.
+    Predicate(Arc<dyn Fn(Window) -> bool + Send + Sync>)
+}
+
+impl RequestorPolicy {
+    pub fn allows(&self, requestor: Window) -> bool {
+        match *self {
+            RequestorPolicy::AllowAll => true,
+            RequestorPolicy::DenyAll => false,
+            RequestorPolicy::Allow(ref windows) => windows.contains(&requestor),
+            RequestorPolicy::Deny(ref windows) => !windows.contains(&requestor),
+            RequestorPolicy::Predicate(ref predicate) => predicate(requestor)
+        }
+    }
+}
+
+impl fmt::Debug for RequestorPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            RequestorPolicy::AllowAll => f.write_str("AllowAll"),
+            RequestorPolicy::DenyAll => f.write_str("DenyAll"),
+            RequestorPolicy::Allow(ref windows) => f.debug_tuple("Allow").field(windows).finish(),
+            RequestorPolicy::Deny(ref windows) => f.debug_tuple("Deny").field(windows).finish(),
+            RequestorPolicy::Predicate(_) => f.write_str("Predicate(..)")
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use std::sync::Mutex;
+use xcb::{ Connection, Atom };
+use error::Error;
+use ::get_atom;
+
+/// Most property atoms a single `Context` will ever intern for its pool.
+pub const PROPERTY_POOL_LIMIT: usize = 16;
+
+/// Property atoms handed out to loads that don't name their own.
+///
+/// The server never frees atoms, so interning a fresh name per load would grow its
+/// atom table for as long as the process runs. Names are reused instead:
+/// `THIS_CLIPBOARD_OUT_<n>` is only interned for `n` up to the number of loads that
+/// were in flight at once, and never past `PROPERTY_POOL_LIMIT`.
+#[derive(Default)]
+pub struct PropertyPool {
+    inner: Mutex<Inner>
+}
+
+#[derive(Default)]
+struct Inner {
+    free: Vec<Atom>,
+    created: usize
+}
+
+/// A property atom checked out of a pool, returned to it on drop.
+pub struct PooledProperty<'a> {
+    pool: &'a PropertyPool,
+    atom: Atom
+}
+
+impl PropertyPool {
+    pub fn checkout<'a>(&'a self, connection: &Connection) -> Result<PooledProperty<'a>, Error> {
+        let mut inner = self.inner.lock().map_err(|_| Error::Lock)?;
+
+        let atom = match inner.free.pop() {
+            Some(atom) => atom,
+            None if inner.created < PROPERTY_POOL_LIMIT => {
+                let atom = get_atom(connection, &format!("THIS_CLIPBOARD_OUT_{}", inner.created))?;
+                inner.created += 1;
+                atom
+            },
+            None => return Err(Error::PropertyPoolExhausted)
+        };
+
+        Ok(PooledProperty { pool: self, atom })
+    }
+}
+
+impl<'a> PooledProperty<'a> {
+    pub fn atom(&self) -> Atom {
+        self.atom
+    }
+}
+
+impl<'a> Drop for PooledProperty<'a> {
+    fn drop(&mut self) {
+        if let Ok(mut inner) = self.pool.inner.lock() {
+            inner.free.push(self.atom);
+        }
+    }
+}
@@ -1,9 +1,13 @@
 use std::cmp;
+use std::thread;
+use std::time::Instant;
 use std::sync::Arc;
-use std::sync::mpsc::Receiver;
+use std::sync::mpsc::{ Sender, Receiver, TryRecvError, channel };
 use std::collections::HashMap;
-use xcb::{ self, Atom };
-use ::{ INCR_CHUNK_SIZE, Context, SetMap };
+use xcb::{ self, Atom, Window };
+use error::Error;
+use ::{ Context, SetMap, Released, Entry, Value, Provider, StoreProgress, connection_error, read_setmap, write_setmap };
+use policy::SharedPolicy;
 
 macro_rules! try_continue {
     ( $expr:expr ) => {
@@ -14,112 +18,658 @@ macro_rules! try_continue {
     };
 }
 
+/// Size of a `ChangeProperty` request without its data.
+const PROPERTY_OVERHEAD: usize = 24;
+
 struct IncrState {
     selection: Atom,
+    target: Atom,
+    type_: Atom,
+    format: u8,
     requestor: Atom,
     property: Atom,
-    pos: usize
+    pos: usize,
+    chunks: usize,
+    /// data computed for this request, rather than stored in the setmap.
+    data: Option<Vec<u8>>
 }
 
-pub fn run(context: &Arc<Context>, setmap: &SetMap, max_length: usize, receiver: &Receiver<Atom>) {
-    let mut incr_map = HashMap::new();
-    let mut state_map = HashMap::new();
+/// Data to answer a request with.
+enum Payload<'a> {
+    Stored(&'a [u8]),
+    Computed(Vec<u8>)
+}
 
-    while let Some(event) = context.connection.wait_for_event() {
-        while let Ok(selection) = receiver.try_recv() {
-            if let Some(property) = incr_map.remove(&selection) {
-                state_map.remove(&property);
-            }
+impl<'a> Payload<'a> {
+    fn as_slice(&self) -> &[u8] {
+        match *self {
+            Payload::Stored(value) => value,
+            Payload::Computed(ref value) => value
         }
+    }
+}
 
-        match event.response_type() & !0x80 {
-            xcb::SELECTION_REQUEST => {
-                let event = unsafe { xcb::cast_event::<xcb::SelectionRequestEvent>(&event) };
-                let read_map = try_continue!(setmap.read().ok());
-                let &(target, ref value) = try_continue!(read_map.get(&event.selection()));
+/// The parts of a `SelectionRequest` needed to answer it later.
+#[derive(Clone, Copy)]
+struct Request {
+    time: xcb::Timestamp,
+    requestor: Window,
+    selection: Atom,
+    target: Atom,
+    property: Atom
+}
 
-                if event.target() == context.atoms.targets {
-                    xcb::change_property(
-                        &context.connection, xcb::PROP_MODE_REPLACE as u8,
-                        event.requestor(), event.property(), xcb::ATOM_ATOM, 32,
-                        &[context.atoms.targets, target]
-                    );
-                } else if value.len() < max_length - 24 {
-                    xcb::change_property(
-                        &context.connection, xcb::PROP_MODE_REPLACE as u8,
-                        event.requestor(), event.property(), target, 8,
-                        value
-                    );
-                } else {
-                    xcb::change_window_attributes(
-                        &context.connection, event.requestor(),
-                        &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)]
-                    );
-                    xcb::change_property(
-                        &context.connection, xcb::PROP_MODE_REPLACE as u8,
-                        event.requestor(), event.property(), context.atoms.incr, 32,
-                        &[0u8; 0]
-                    );
+/// How a request was answered.
+enum Answer {
+    /// written to the property, `complete` if that includes all of some data.
+    Sent { complete: bool },
+    /// written, and the selection is to be given up, see `DELETE`.
+    Deleted,
+    Refused,
+    /// the value for `source` has to be asked for first.
+    Deferred(Atom, Provider)
+}
 
-                    incr_map.insert(event.selection(), event.property());
-                    state_map.insert(
-                        event.property(),
-                        IncrState {
-                            selection: event.selection(),
-                            requestor: event.requestor(),
-                            property: event.property(),
-                            pos: 0
-                        }
-                    );
+/// A deferred value that is ready, or `None` if its provider gave up.
+struct Ready {
+    request: Request,
+    source: Atom,
+    provider: Provider,
+    value: Option<Vec<u8>>
+}
+
+/// Targets that older toolkits such as Motif ask for,
+/// served from the data stored under their modern equivalents.
+struct Legacy {
+    motif_targets: Atom,
+    text: Atom,
+    compound_text: Atom
+}
+
+impl Legacy {
+    fn new(context: &Context) -> Legacy {
+        let get_atom = |name| context.get_atom(name).unwrap_or(xcb::ATOM_NONE);
+        Legacy {
+            motif_targets: get_atom("_MOTIF_CLIPBOARD_TARGETS"),
+            text: get_atom("TEXT"),
+            compound_text: get_atom("COMPOUND_TEXT")
+        }
+    }
+}
+
+struct Worker<'a> {
+    context: &'a Arc<Context>,
+    setmap: &'a SetMap,
+    policy: &'a SharedPolicy,
+    released: &'a Released,
+    legacy: Legacy,
+    max_length: usize,
+    chunk_size: usize,
+    incr_map: HashMap<Atom, Atom>,
+    state_map: HashMap<Atom, IncrState>,
+    ready: Sender<Ready>,
+    /// the earliest `expires` among the stored entries.
+    next_expiry: Option<Instant>
+}
+
+/// the largest value sent in a single `ChangeProperty`, larger ones go through INCR.
+pub fn max_inline_size(context: &Context) -> usize {
+    context.connection.get_maximum_request_length() as usize * 4 - PROPERTY_OVERHEAD
+}
+
+pub fn run(context: &Arc<Context>, setmap: &SetMap, policy: &SharedPolicy, released: &Released, max_length: usize, chunk_size: usize, receiver: &Receiver<Atom>)
+    -> Result<(), Error>
+{
+    let (ready_sender, ready_receiver) = channel();
+    let mut worker = Worker {
+        context, setmap, policy, released, max_length,
+        legacy: Legacy::new(context),
+        // Every chunk is sent as a single request, whatever size was asked for.
+        chunk_size: cmp::min(chunk_size, max_length - PROPERTY_OVERHEAD),
+        incr_map: HashMap::new(),
+        state_map: HashMap::new(),
+        ready: ready_sender,
+        next_expiry: None
+    };
+    worker.schedule();
+
+    let (min_poll, max_poll) = context.poll_backoff;
+    let mut poll = min_poll;
+
+    loop {
+        let event = match worker.next_expiry {
+            // nothing to expire, so nothing to do until an event comes.
+            None => match context.connection.wait_for_event() {
+                Some(event) => event,
+                None => break
+            },
+            Some(_) => match context.connection.poll_for_event() {
+                Some(event) => {
+                    poll = min_poll;
+                    event
+                },
+                None if context.connection.has_error().is_err() => break,
+                None => {
+                    worker.expire();
+                    // Don't sleep past the next expiry.
+                    let now = Instant::now();
+                    let left = worker.next_expiry.map_or(poll, |next| next.saturating_duration_since(now));
+                    thread::park_timeout(cmp::min(poll, left));
+                    poll = cmp::min(poll * 2, max_poll);
+                    continue
                 }
+            }
+        };
 
-                xcb::send_event(
-                    &context.connection, false, event.requestor(), 0,
-                    &xcb::SelectionNotifyEvent::new(
-                        event.time(),
-                        event.requestor(),
-                        event.selection(),
-                        event.target(),
-                        event.property()
-                    )
-                );
-                context.connection.flush();
+        loop {
+            match receiver.try_recv() {
+                Ok(selection) => worker.reset(selection),
+                Err(TryRecvError::Empty) => break,
+                // the `Clipboard` is gone
+                Err(TryRecvError::Disconnected) => return Ok(())
+            }
+        }
+
+        while let Ok(ready) = ready_receiver.try_recv() {
+            worker.handle_ready(ready);
+        }
+
+        worker.expire();
+
+        match event.response_type() & !0x80 {
+            xcb::SELECTION_REQUEST => {
+                let event = unsafe { xcb::cast_event::<xcb::SelectionRequestEvent>(&event) };
+                worker.handle_request(Request {
+                    time: event.time(),
+                    requestor: event.requestor(),
+                    selection: event.selection(),
+                    target: event.target(),
+                    property: event.property()
+                });
             },
             xcb::PROPERTY_NOTIFY => {
                 let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+                if event.window() == context.window && event.atom() == context.atoms.timestamp {
+                    stamp(setmap, event.time());
+                    continue
+                }
                 if event.state() != xcb::PROPERTY_DELETE as u8 { continue };
+                if worker.drop_removed(event.atom()) { continue };
 
-                let is_end = {
-                    let state = try_continue!(state_map.get_mut(&event.atom()));
-                    let read_setmap = try_continue!(setmap.read().ok());
-                    let &(target, ref value) = try_continue!(read_setmap.get(&state.selection));
+                let (is_end, selection, ephemeral, sent, chunks) = {
+                    let state = try_continue!(worker.state_map.get_mut(&event.atom()));
+                    let read_map = read_setmap(setmap);
+                    let entry = try_continue!(read_map.get(&state.selection));
+                    let value = match state.data {
+                        Some(ref data) => data.as_slice(),
+                        None => try_continue!(entry.get(state.target).and_then(Value::bytes))
+                    };
 
-                    let len = cmp::min(INCR_CHUNK_SIZE, value.len() - state.pos);
-                    xcb::change_property(
-                        &context.connection, xcb::PROP_MODE_REPLACE as u8,
-                        state.requestor, state.property, target, 8,
-                        &value[state.pos..][..len]
+                    // 32-bit data is cut on item boundaries.
+                    let unit = state.format as usize / 8;
+                    let chunk_size = cmp::max(worker.chunk_size / unit * unit, unit);
+                    // the value may have been replaced by a shorter one meanwhile,
+                    // then there is nothing left to send.
+                    let rest = value.get(state.pos..).unwrap_or(&[]);
+                    let len = cmp::min(chunk_size, rest.len());
+                    change_property(
+                        context, state.requestor, state.property, state.type_, state.format,
+                        &rest[..len]
                     );
 
                     state.pos += len;
-                    len == 0
+                    if len > 0 {
+                        state.chunks += 1;
+                    }
+                    trace!("sent {} of {} bytes to window {}", state.pos, value.len(), state.requestor);
+                    if len > 0 {
+                        entry.progressed(StoreProgress {
+                            selection: state.selection,
+                            requestor: state.requestor,
+                            sent: state.pos,
+                            total: value.len()
+                        });
+                    }
+                    (len == 0, state.selection, entry.ephemeral, state.pos, state.chunks)
                 };
 
                 if is_end {
-                    state_map.remove(&event.atom());
+                    debug!("finished INCR of {}", context.atoms.label(selection));
+                    if let Some(ref metrics) = context.metrics {
+                        metrics.on_incr_transfer(selection, sent, chunks);
+                    }
+                    worker.state_map.remove(&event.atom());
+                    if ephemeral {
+                        worker.incr_map.remove(&selection);
+                        relinquish(context, setmap, released, selection);
+                    }
                 }
                 context.connection.flush();
             },
             xcb::SELECTION_CLEAR => {
                 let event = unsafe { xcb::cast_event::<xcb::SelectionClearEvent>(&event) };
-                if let Some(property) = incr_map.remove(&event.selection()) {
-                    state_map.remove(&property);
+                debug!("lost ownership of {}", context.atoms.label(event.selection()));
+                worker.reset(event.selection());
+                forget(setmap, released, event.selection());
+            },
+            // `wakeup`, a store may have brought an earlier expiry.
+            xcb::CLIENT_MESSAGE => worker.schedule(),
+            _ => ()
+        }
+    }
+
+    Err(connection_error(&context.connection))
+}
+
+impl<'a> Worker<'a> {
+    /// forget any INCR transfer in progress for the selection.
+    fn reset(&mut self, selection: Atom) {
+        if let Some(property) = self.incr_map.remove(&selection) {
+            self.state_map.remove(&property);
+        }
+    }
+
+    /// forget the INCR transfer on `property` if its target is no longer stored,
+    /// see `Clipboard::remove_target`.
+    fn drop_removed(&mut self, property: Atom) -> bool {
+        let selection = match self.state_map.get(&property) {
+            Some(state) => match read_setmap(self.setmap).get(&state.selection) {
+                Some(entry) if entry.get(state.target).is_none() => state.selection,
+                _ => return false
+            },
+            None => return false
+        };
+
+        debug!("dropped INCR of a removed target of {}", self.context.atoms.label(selection));
+        self.state_map.remove(&property);
+        if self.incr_map.get(&selection) == Some(&property) {
+            self.incr_map.remove(&selection);
+        }
+        true
+    }
+
+    /// find when the next stored entry expires, so the event loop knows how
+    /// long it may block.
+    fn schedule(&mut self) {
+        self.next_expiry = read_setmap(self.setmap).values()
+            .filter_map(|entry| entry.expires)
+            .min();
+    }
+
+    /// relinquish every selection stored with a `ttl` that has passed.
+    fn expire(&mut self) {
+        let now = Instant::now();
+        match self.next_expiry {
+            Some(next) if next <= now => (),
+            _ => return
+        }
+
+        let expired = {
+            let (ref lock, ref condvar) = **self.released;
+            let _guard = lock.lock();
+            let mut write_map = write_setmap(self.setmap);
+
+            let expired = write_map.iter()
+                .filter(|&(_, entry)| entry.expires.into_iter().any(|expires| expires <= now))
+                .map(|(&selection, entry)| (selection, entry.acquired))
+                .collect::<Vec<_>>();
+            for &(selection, _) in &expired {
+                write_map.remove(&selection);
+            }
+            condvar.notify_all();
+            expired
+        };
+
+        for (selection, acquired) in expired {
+            self.reset(selection);
+            disown(self.context, selection, acquired);
+        }
+        self.context.connection.flush();
+        self.schedule();
+    }
+
+    fn handle_request(&mut self, request: Request) {
+        let (context, setmap) = (self.context, self.setmap);
+        debug!(
+            "request for {} of {} from window {}",
+            context.atoms.label(request.target), context.atoms.label(request.selection), request.requestor
+        );
+        if !self.allows(request.requestor) {
+            debug!("refused window {} by policy", request.requestor);
+            return notify(context, &request, xcb::ATOM_NONE);
+        }
+
+        let read_map = read_setmap(setmap);
+
+        let (answer, ephemeral) = match read_map.get(&request.selection) {
+            Some(entry) if entry.requestor.is_some() && entry.requestor != Some(request.requestor) =>
+                (Answer::Refused, false),
+            Some(entry) if request.target == context.atoms.multiple =>
+                (self.answer_multiple(entry, &request), entry.ephemeral),
+            Some(entry) => (self.answer(entry, &request), entry.ephemeral),
+            None => (Answer::Refused, false)
+        };
+        drop(read_map);
+
+        let property = match answer {
+            Answer::Sent { complete } => {
+                // Give up ownership before notifying, so the requestor
+                // can't get a second paste in before the owner changes.
+                if complete && ephemeral {
+                    relinquish(context, setmap, self.released, request.selection);
                 }
-                if let Ok(mut write_setmap) = setmap.write() {
-                    write_setmap.remove(&event.selection());
+                request.property
+            },
+            Answer::Deleted => {
+                relinquish(context, setmap, self.released, request.selection);
+                request.property
+            },
+            Answer::Refused => {
+                debug!("refused {} of {}", context.atoms.label(request.target), context.atoms.label(request.selection));
+                xcb::ATOM_NONE
+            },
+            Answer::Deferred(source, provider) => {
+                // Answered from `handle_ready` once the value arrives.
+                return self.defer(request, source, provider);
+            }
+        };
+
+        notify(context, &request, property);
+    }
+
+    /// write the value for a single target to the request's property.
+    fn answer(&mut self, entry: &Entry, request: &Request) -> Answer {
+        let context = self.context;
+
+        if request.target == context.atoms.targets || request.target == self.legacy.motif_targets {
+            let mut targets = vec![context.atoms.targets, context.atoms.multiple, context.atoms.timestamp];
+            match entry.targets.first() {
+                Some(&(_, Value::Deferred(_))) | Some(&(_, Value::Dynamic(_))) | None => (),
+                Some(_) => targets.push(context.atoms.length)
+            }
+            targets.extend(entry.targets.iter().map(|&(target, _)| target));
+            xcb::change_property(
+                &context.connection, xcb::PROP_MODE_REPLACE as u8,
+                request.requestor, request.property, xcb::ATOM_ATOM, 32,
+                &targets
+            );
+            return Answer::Sent { complete: false };
+        }
+
+        // ICCCM: the owner deletes the selection and replies with an empty `NULL`,
+        // it's given up in `handle_request`.
+        if request.target == context.atoms.delete {
+            xcb::change_property(
+                &context.connection, xcb::PROP_MODE_REPLACE as u8,
+                request.requestor, request.property, context.atoms.null, 8,
+                &[0u8; 0]
+            );
+            return Answer::Deleted;
+        }
+
+        if request.target == context.atoms.timestamp {
+            xcb::change_property(
+                &context.connection, xcb::PROP_MODE_REPLACE as u8,
+                request.requestor, request.property, xcb::ATOM_INTEGER, 32,
+                &[entry.acquired]
+            );
+            return Answer::Sent { complete: false };
+        }
+
+        // `LENGTH` is the size of the first target's data.
+        if request.target == context.atoms.length {
+            return match entry.targets.first().and_then(|(_, value)| value.len()) {
+                Some(len) => {
+                    xcb::change_property(
+                        &context.connection, xcb::PROP_MODE_REPLACE as u8,
+                        request.requestor, request.property, xcb::ATOM_CARDINAL, 32,
+                        &[len as u32]
+                    );
+                    Answer::Sent { complete: false }
+                },
+                None => Answer::Refused
+            };
+        }
+
+        let complete = match self.lookup(entry, request.target) {
+            Some((source, type_, Value::Bytes(value))) =>
+                self.send(request, source, type_, 8, Payload::Stored(value)),
+            Some((source, type_, Value::Shared(value))) =>
+                self.send(request, source, type_, 8, Payload::Stored((**value).as_ref())),
+            Some((source, _, Value::Typed { type_, format, data })) =>
+                self.send(request, source, *type_, *format, Payload::Stored(data)),
+            Some((source, type_, Value::Derived(data, derive))) => {
+                let value = derive(data);
+                self.send(request, source, type_, 8, Payload::Computed(value))
+            },
+            Some((source, _, Value::Deferred(provider))) =>
+                return Answer::Deferred(source, Arc::clone(provider)),
+            Some((source, type_, Value::Dynamic(provider))) => {
+                let value = provider.lock().ok().and_then(|mut provider| provider(request.requestor, source));
+                match value {
+                    Some(value) => self.send(request, source, type_, 8, Payload::Computed(value)),
+                    None => return Answer::Refused
                 }
             },
-            _ => ()
+            None => return Answer::Refused
+        };
+        entry.served(request.requestor);
+        Answer::Sent { complete }
+    }
+
+    /// answer each (target, property) pair the requestor listed in the request's
+    /// property, replacing the property of those that can't be answered with `None`.
+    fn answer_multiple(&mut self, entry: &Entry, request: &Request) -> Answer {
+        let context = self.context;
+        let reply = xcb::get_property(
+            &context.connection, false, request.requestor, request.property,
+            xcb::ATOM_ANY, 0, u32::MAX
+        ).get_reply();
+        let reply = match reply {
+            Ok(ref reply) if reply.format() == 32 && reply.type_() != xcb::ATOM_NONE => reply,
+            _ => return Answer::Refused
+        };
+
+        let mut pairs = reply.value::<Atom>().to_vec();
+        let mut complete = false;
+        let mut deleted = false;
+        for pair in pairs.chunks_exact_mut(2) {
+            if pair[1] == xcb::ATOM_NONE { continue };
+            let single = Request { target: pair[0], property: pair[1], ..*request };
+            match self.answer(entry, &single) {
+                Answer::Sent { complete: sent } => complete |= sent,
+                Answer::Deleted => deleted = true,
+                // there's no waiting for a provider in the middle of the reply.
+                Answer::Refused | Answer::Deferred(..) => pair[1] = xcb::ATOM_NONE
+            }
         }
+
+        xcb::change_property(
+            &context.connection, xcb::PROP_MODE_REPLACE as u8,
+            request.requestor, request.property, reply.type_(), 32,
+            &pairs
+        );
+        match deleted {
+            true => Answer::Deleted,
+            false => Answer::Sent { complete }
+        }
+    }
+
+    fn allows(&self, requestor: Window) -> bool {
+        match self.policy.read() {
+            Ok(policy) => policy.allows(requestor),
+            // fail closed
+            Err(_) => false
+        }
+    }
+
+    /// write the value to the requestor's property, or start an INCR transfer if
+    /// it's too large for a single request.
+    ///
+    /// Returns whether the value was sent in full.
+    fn send(&mut self, request: &Request, source: Atom, type_: Atom, format: u8, payload: Payload) -> bool {
+        let context = self.context;
+        let value = payload.as_slice();
+
+        if value.len() <= self.max_length - PROPERTY_OVERHEAD {
+            change_property(context, request.requestor, request.property, type_, format, value);
+            return true;
+        }
+
+        xcb::change_window_attributes(
+            &context.connection, request.requestor,
+            &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)]
+        );
+        xcb::change_property(
+            &context.connection, xcb::PROP_MODE_REPLACE as u8,
+            request.requestor, request.property, context.atoms.incr, 32,
+            &[0u8; 0]
+        );
+
+        debug!(
+            "starting INCR of {} bytes of {} to window {}",
+            value.len(), context.atoms.label(source), request.requestor
+        );
+        self.incr_map.insert(request.selection, request.property);
+        self.state_map.insert(
+            request.property,
+            IncrState {
+                selection: request.selection,
+                target: source,
+                type_,
+                format,
+                requestor: request.requestor,
+                property: request.property,
+                pos: 0,
+                chunks: 0,
+                data: match payload {
+                    Payload::Stored(_) => None,
+                    Payload::Computed(value) => Some(value)
+                }
+            }
+        );
+        false
+    }
+
+    /// find the value for a target, looking through legacy aliases.
+    ///
+    /// Returns the target the value is stored under and the type to reply with.
+    fn lookup<'e>(&self, entry: &'e Entry, target: Atom) -> Option<(Atom, Atom, &'e Value)> {
+        let atoms = &self.context.atoms;
+        let (source, type_) = if entry.get(target).is_some() {
+            (target, target)
+        } else if target == self.legacy.text {
+            // `TEXT` is answered in whichever encoding we have.
+            let source = [atoms.utf8_string, atoms.string].iter()
+                .cloned()
+                .find(|&t| entry.get(t).is_some())?;
+            (source, source)
+        } else if target == self.legacy.compound_text {
+            // Latin-1 text is valid compound text as is.
+            (atoms.string, target)
+        } else {
+            return None
+        };
+
+        entry.get(source).map(|value| (source, type_, value))
+    }
+
+    /// ask the provider for a value and wait for it on a helper thread.
+    fn defer(&self, request: Request, source: Atom, provider: Provider) {
+        let receiver = provider();
+        let ready = self.ready.clone();
+        let context = Arc::clone(self.context);
+
+        thread::spawn(move || {
+            let value = receiver.recv().ok();
+            if ready.send(Ready { request, source, provider, value }).is_ok() {
+                wakeup(&context);
+            }
+        });
+    }
+
+    fn handle_ready(&mut self, ready: Ready) {
+        let Ready { request, source, provider, value } = ready;
+
+        let value = match value {
+            Some(value) => value,
+            None => return notify(self.context, &request, xcb::ATOM_NONE)
+        };
+
+        // Keep the value, so later requests are served without asking again.
+        if let Some(entry) = write_setmap(self.setmap).get_mut(&request.selection) {
+            entry.resolve(source, &provider, value);
+        }
+
+        self.handle_request(request);
+    }
+}
+
+/// replace the property with `data`, which holds native-endian items for format 32.
+fn change_property(context: &Context, window: Window, property: Atom, type_: Atom, format: u8, data: &[u8]) {
+    if format == 32 {
+        let data = data.chunks_exact(4)
+            .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+            .collect::<Vec<_>>();
+        xcb::change_property(&context.connection, xcb::PROP_MODE_REPLACE as u8, window, property, type_, 32, &data);
+    } else {
+        xcb::change_property(&context.connection, xcb::PROP_MODE_REPLACE as u8, window, property, type_, format, data);
+    }
+}
+
+fn notify(context: &Context, request: &Request, property: Atom) {
+    xcb::send_event(
+        &context.connection, false, request.requestor, 0,
+        &xcb::SelectionNotifyEvent::new(
+            request.time,
+            request.requestor,
+            request.selection,
+            request.target,
+            property
+        )
+    );
+    context.connection.flush();
+}
+
+/// give up the selection we took at `acquired`. The server ignores this if
+/// someone took it since, so someone else's ownership is never cleared.
+pub fn disown(context: &Context, selection: Atom, acquired: xcb::Timestamp) {
+    xcb::set_selection_owner(&context.connection, xcb::NONE, selection, acquired);
+}
+
+/// wake the worker out of `wait_for_event`, so that it checks its channels.
+pub fn wakeup(context: &Context) {
+    let data = xcb::ClientMessageData::from_data32([0; 5]);
+    xcb::send_event(
+        &context.connection, false, context.window, xcb::EVENT_MASK_NO_EVENT,
+        &xcb::ClientMessageEvent::new(32, context.window, xcb::ATOM_NONE, data)
+    );
+    context.connection.flush();
+}
+
+/// drop the stored entry once the selection is no longer ours, and wake anyone
+/// waiting for that.
+pub fn forget(setmap: &SetMap, released: &Released, selection: Atom) {
+    let (ref lock, ref condvar) = **released;
+    let _guard = lock.lock();
+    write_setmap(setmap).remove(&selection);
+    condvar.notify_all();
+}
+
+/// give entries stored without a timestamp the time of the `PropertyNotify` the
+/// store caused, just before it took the selection.
+fn stamp(setmap: &SetMap, time: xcb::Timestamp) {
+    for entry in write_setmap(setmap).values_mut() {
+        if entry.acquired == xcb::CURRENT_TIME {
+            entry.acquired = time;
+        }
+    }
+}
+
+fn relinquish(context: &Context, setmap: &SetMap, released: &Released, selection: Atom) {
+    let acquired = read_setmap(setmap).get(&selection).map(|entry| entry.acquired);
+    forget(setmap, released, selection);
+    if let Some(acquired) = acquired {
+        disown(context, selection, acquired);
     }
 }
@@ -0,0 +1,79 @@
+use std::io::Write;
+use std::sync::mpsc::SyncSender;
+use error::Error;
+
+/// Where `Context::process_event` puts the data as it arrives.
+pub(crate) trait Sink {
+    /// room for `additional` more bytes is about to be needed.
+    fn reserve(&mut self, _additional: usize) {}
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error>;
+
+    /// bytes written so far.
+    fn len(&self) -> usize;
+}
+
+impl Sink for Vec<u8> {
+    fn reserve(&mut self, additional: usize) {
+        Vec::reserve(self, additional);
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.extend_from_slice(data);
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        Vec::len(self)
+    }
+}
+
+/// Writes the data through to `W` as it arrives, rather than keeping it.
+pub(crate) struct WriteSink<W: Write> {
+    pub writer: W,
+    len: usize
+}
+
+impl<W: Write> WriteSink<W> {
+    pub fn new(writer: W) -> WriteSink<W> {
+        WriteSink { writer, len: 0 }
+    }
+}
+
+impl<W: Write> Sink for WriteSink<W> {
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.writer.write_all(data)?;
+        self.len += data.len();
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+/// Sends each piece of data down a channel as it arrives.
+pub(crate) struct ChannelSink {
+    sender: SyncSender<Result<Vec<u8>, Error>>,
+    len: usize
+}
+
+impl ChannelSink {
+    pub fn new(sender: SyncSender<Result<Vec<u8>, Error>>) -> ChannelSink {
+        ChannelSink { sender, len: 0 }
+    }
+}
+
+impl Sink for ChannelSink {
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        if data.is_empty() { return Ok(()) };
+        // nobody is listening any more, stop the load.
+        self.sender.send(Ok(data.to_vec())).map_err(|_| Error::Cancelled)?;
+        self.len += data.len();
+        Ok(())
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+}
@@ -0,0 +1,45 @@
+use std::time::Duration;
+use std::collections::HashMap;
+use xcb::{ self, Atom };
+#[cfg(feature = "serde")]
+use serde::{ Serialize, Deserialize };
+use error::Error;
+use ::Clipboard;
+
+/// Every data target of a selection by name, see `Clipboard::export`.
+///
+/// Names rather than atoms, so it stays meaningful on another connection or machine.
+/// With the `serde` feature it can be serialized.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ClipboardSnapshot {
+    pub targets: Vec<(String, Vec<u8>)>
+}
+
+impl Clipboard {
+    /// take a `snapshot` of the selection with the targets named.
+    pub fn export<T>(&self, selection: Atom, timeout: T)
+        -> Result<ClipboardSnapshot, Error>
+        where T: Into<Option<Duration>>
+    {
+        let snapshot = self.snapshot(selection, timeout)?;
+        let cookies = snapshot.iter()
+            .map(|&(target, _)| xcb::get_atom_name(&self.getter.connection, target))
+            .collect::<Vec<_>>();
+
+        let mut targets = Vec::with_capacity(snapshot.len());
+        for (cookie, (_, value)) in cookies.into_iter().zip(snapshot) {
+            targets.push((cookie.get_reply()?.name().to_owned(), value));
+        }
+        Ok(ClipboardSnapshot { targets })
+    }
+
+    /// store what `export` took, possibly somewhere else, back into the selection.
+    pub fn import(&self, selection: Atom, snapshot: ClipboardSnapshot) -> Result<(), Error> {
+        let mut targets = HashMap::with_capacity(snapshot.targets.len());
+        for (name, value) in snapshot.targets {
+            targets.insert(self.setter.get_atom(&name)?, value);
+        }
+        self.store_multiple(selection, targets)
+    }
+}
@@ -0,0 +1,74 @@
+use std::thread;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::mpsc::{ sync_channel, Receiver };
+use xcb::{ self, Atom };
+use error::Error;
+use sink::ChannelSink;
+use ::{ Clipboard, Context, Deadline, LoadControl, LoadOptions };
+
+/// How many chunks may wait for the consumer before the transfer holds back.
+const STREAM_BACKLOG: usize = 4;
+
+/// The chunks of a `Clipboard::load_stream`, dropping it cancels the load.
+pub struct LoadStream {
+    receiver: Option<Receiver<Result<Vec<u8>, Error>>>,
+    control: Arc<LoadControl>
+}
+
+impl Iterator for LoadStream {
+    type Item = Result<Vec<u8>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let item = self.receiver.as_ref()?.recv().ok();
+        // nothing comes after an error.
+        if item.as_ref().is_none_or(Result::is_err) {
+            self.receiver = None;
+        }
+        item
+    }
+}
+
+impl Drop for LoadStream {
+    fn drop(&mut self) {
+        // the helper may be waiting on an owner that never answers, not on us.
+        self.control.cancel();
+    }
+}
+
+impl Clipboard {
+    /// load value a chunk at a time, each INCR chunk is yielded as it arrives
+    /// and the iterator ends once the transfer is complete.
+    ///
+    /// It runs on a connection and thread of its own. Only a few chunks are
+    /// received ahead of the consumer, so a slow one holds the transfer back.
+    /// A value that doesn't come through INCR is a single chunk, there are none
+    /// if the selection has no owner or the conversion is refused.
+    pub fn load_stream<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<LoadStream, Error>
+        where T: Into<Option<Duration>>
+    {
+        let deadline = Deadline::after(timeout.into());
+        let control = Arc::new(LoadControl::new());
+        let context = Context::new(self.builder.display_name())?;
+        let time = context.server_time()?;
+        if context.request_conversion(selection, target, property, time)?.is_some() {
+            return Ok(LoadStream { receiver: None, control });
+        }
+
+        let (sender, receiver) = sync_channel(STREAM_BACKLOG);
+        let helper_control = Arc::clone(&control);
+        thread::spawn(move || {
+            let mut sink = ChannelSink::new(sender.clone());
+            if let Err(err) = context.process_event(context.window, &mut sink, selection, target, property, LoadOptions {
+                deadline, control: Some(&helper_control), ..LoadOptions::default()
+            }) {
+                let _ = sender.send(Err(err));
+            }
+            xcb::delete_property(&context.connection, context.window, property);
+            context.connection.flush();
+        });
+
+        Ok(LoadStream { receiver: Some(receiver), control })
+    }
+}
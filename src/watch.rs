@@ -0,0 +1,205 @@
+use std::thread;
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::mpsc::{ channel, Receiver, RecvTimeoutError };
+use std::time::{ Duration, Instant };
+use std::sync::atomic::{ AtomicBool, Ordering };
+use xcb::{ self, Atom, Window };
+use xcb::base::ConnError;
+use error::{ Error, TimeoutInfo };
+use ::{ run, connection_error, Clipboard, Context, Deadline };
+
+/// Keeps a `Clipboard::watch` running, it stops when dropped.
+pub struct WatchHandle {
+    context: Arc<Context>,
+    root: Window,
+    selection: Atom,
+    paused: Arc<AtomicBool>,
+    stopped: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<()>>
+}
+
+impl WatchHandle {
+    /// stop calling back, changes meanwhile are dropped.
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    /// call back again for changes from now on.
+    pub fn resume(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        xcb::xfixes::select_selection_input(&self.context.connection, self.root, self.selection, 0);
+        run::wakeup(&self.context);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// A change of selection owner, see `Clipboard::watch_events`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WatchEvent {
+    pub selection: Atom,
+    /// the new owner, `xcb::NONE` if there is none.
+    pub owner: Window
+}
+
+/// The receiving end of `Clipboard::watch_events`, watching stops when it's dropped.
+pub struct WatchEvents {
+    receiver: Receiver<WatchEvent>,
+    handle: WatchHandle
+}
+
+impl WatchEvents {
+    pub fn handle(&self) -> &WatchHandle {
+        &self.handle
+    }
+}
+
+impl Deref for WatchEvents {
+    type Target = Receiver<WatchEvent>;
+
+    fn deref(&self) -> &Receiver<WatchEvent> {
+        &self.receiver
+    }
+}
+
+impl Clipboard {
+    /// call `callback` with the new owner, or `xcb::NONE`, whenever the selection
+    /// changes hands, until the returned handle is dropped.
+    ///
+    /// It runs on its own connection and thread, subscribed through XFIXES.
+    pub fn watch<F>(&self, selection: Atom, mut callback: F) -> Result<WatchHandle, Error>
+        where F: FnMut(Window) + Send + 'static
+    {
+        let context = Arc::new(Context::new(self.builder.display_name())?);
+
+        let xfixes = xcb::query_extension(&context.connection, "XFIXES").get_reply()?;
+        if !xfixes.present() {
+            return Err(Error::XcbConn(ConnError::ClosedExtNotSupported));
+        }
+        xcb::xfixes::query_version(&context.connection, 5, 0).get_reply()?;
+
+        let root = context.screen_info()?.root;
+        xcb::xfixes::select_selection_input(
+            &context.connection, root, selection,
+            xcb::xfixes::SELECTION_EVENT_MASK_SET_SELECTION_OWNER |
+            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_CLIENT_CLOSE |
+            xcb::xfixes::SELECTION_EVENT_MASK_SELECTION_WINDOW_DESTROY);
+        // changes right after we return must not be missed.
+        xcb::get_selection_owner(&context.connection, selection).get_reply()?;
+
+        let paused = Arc::new(AtomicBool::new(false));
+        let stopped = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let (context, paused, stopped) = (Arc::clone(&context), Arc::clone(&paused), Arc::clone(&stopped));
+            let notify_event = xfixes.first_event() + xcb::xfixes::SELECTION_NOTIFY;
+
+            thread::spawn(move || {
+                while let Some(event) = context.connection.wait_for_event() {
+                    if stopped.load(Ordering::SeqCst) { break };
+                    if event.response_type() & !0x80 != notify_event { continue };
+
+                    let event = unsafe { xcb::cast_event::<xcb::xfixes::SelectionNotifyEvent>(&event) };
+                    if event.selection() == selection && !paused.load(Ordering::SeqCst) {
+                        callback(event.owner());
+                    }
+                }
+            })
+        };
+
+        Ok(WatchHandle { context, root, selection, paused, stopped, thread: Some(thread) })
+    }
+
+    /// like `watch`, but sending a `WatchEvent` for each change down a channel.
+    pub fn watch_events(&self, selection: Atom) -> Result<WatchEvents, Error> {
+        let (sender, receiver) = channel();
+        let handle = self.watch(selection, move |owner| {
+            let _ = sender.send(WatchEvent { selection, owner });
+        })?;
+        Ok(WatchEvents { receiver, handle })
+    }
+
+    /// block until the selection changes hands to something that isn't empty
+    /// for `target`, and return it.
+    ///
+    /// Owners that come and go with nothing to paste are skipped, `None` waits forever.
+    pub fn wait_for_nonempty<T>(&self, selection: Atom, target: Atom, property: Atom, timeout: T)
+        -> Result<Vec<u8>, Error>
+        where T: Into<Option<Duration>>
+    {
+        let start = Instant::now();
+        let deadline = timeout.into().map(|timeout| start + timeout);
+        let timed_out = || Error::Timeout(TimeoutInfo { selection, target, elapsed: start.elapsed() });
+        let (sender, receiver) = channel();
+        let handle = self.watch(selection, move |owner| { let _ = sender.send(owner); })?;
+
+        loop {
+            let owner = match deadline {
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline { return Err(timed_out()) };
+                    match receiver.recv_timeout(deadline - now) {
+                        Ok(owner) => owner,
+                        Err(RecvTimeoutError::Timeout) => return Err(timed_out()),
+                        Err(RecvTimeoutError::Disconnected) => return Err(connection_error(&handle.context.connection))
+                    }
+                },
+                None => receiver.recv().map_err(|_| connection_error(&handle.context.connection))?
+            };
+            if owner == xcb::NONE { continue };
+
+            let remaining = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            match self.load(selection, target, property, remaining) {
+                Ok(value) if !value.is_empty() => return Ok(value),
+                Ok(_) | Err(Error::UnexpectedType(_)) | Err(Error::NoOwner) => (),
+                // the owner may already be gone again, keep waiting.
+                Err(Error::Timeout(_)) if deadline.is_none_or(|deadline| Instant::now() < deadline) => (),
+                Err(err) => return Err(err)
+            }
+        }
+    }
+
+    /// block until some client, this one or another, owns the selection, and return
+    /// the owner's window. If it's owned already, that owner is returned at once.
+    ///
+    /// Nothing is loaded, `None` waits forever.
+    pub fn wait_for_owner<T>(&self, selection: Atom, timeout: T)
+        -> Result<Window, Error>
+        where T: Into<Option<Duration>>
+    {
+        let start = Instant::now();
+        let deadline = Deadline::after(timeout.into());
+        let (sender, receiver) = channel();
+        // subscribed before looking, so an owner coming in between isn't missed.
+        let handle = self.watch(selection, move |owner| { let _ = sender.send(owner); })?;
+
+        let mut owner = xcb::get_selection_owner(&self.getter.connection, selection)
+            .get_reply()?
+            .owner();
+        while owner == xcb::NONE {
+            owner = match deadline.remaining() {
+                Some(remaining) => match receiver.recv_timeout(remaining) {
+                    Ok(owner) => owner,
+                    Err(RecvTimeoutError::Timeout) => {
+                        let elapsed = start.elapsed();
+                        return Err(Error::Timeout(TimeoutInfo { selection, target: xcb::ATOM_NONE, elapsed }));
+                    },
+                    Err(RecvTimeoutError::Disconnected) => return Err(connection_error(&handle.context.connection))
+                },
+                None => receiver.recv().map_err(|_| connection_error(&handle.context.connection))?
+            };
+        }
+        Ok(owner)
+    }
+}
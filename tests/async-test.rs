@@ -0,0 +1,45 @@
+#![cfg(feature = "async")]
+
+extern crate x11_clipboard;
+
+use std::thread;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{ Context, Poll, Wake, Waker };
+use std::time::Duration;
+use x11_clipboard::Clipboard;
+
+
+struct Unpark(thread::Thread);
+
+impl Wake for Unpark {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// just enough of an executor to drive a single future.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park()
+        }
+    }
+}
+
+#[test]
+fn it_load_async() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_ASYNC").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, "awaited").unwrap();
+
+    let future = clipboard.load_async(selection, atom_utf8string, atom_property, Duration::from_secs(3));
+    assert_eq!(block_on(future).unwrap(), b"awaited");
+}
@@ -0,0 +1,22 @@
+#![cfg(feature = "bytes")]
+
+extern crate x11_clipboard;
+
+use std::time::Duration;
+use x11_clipboard::Clipboard;
+
+
+#[test]
+fn it_load_bytes() {
+    let data = "lorem ipsum dolor sit amet ".repeat(10_000).into_bytes();
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_BYTES").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, data.clone()).unwrap();
+
+    let output = clipboard.load_bytes(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(&output[..], &data[..]);
+}
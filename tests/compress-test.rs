@@ -0,0 +1,25 @@
+#![cfg(feature = "compression")]
+
+extern crate x11_clipboard;
+
+use std::time::Duration;
+use x11_clipboard::Clipboard;
+
+
+#[test]
+fn it_round_trip_compressed() {
+    let data = "lorem ipsum dolor sit amet ".repeat(10_000).into_bytes();
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_COMPRESSED").unwrap();
+    let target = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_ZLIB").unwrap();
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store_compressed(selection, target, &data).unwrap();
+
+    let raw = clipboard.load(selection, target, atom_property, Duration::from_secs(3)).unwrap();
+    assert!(raw.len() * 10 < data.len());
+
+    let output = clipboard.load_compressed(selection, target, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, data);
+}
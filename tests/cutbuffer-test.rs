@@ -0,0 +1,32 @@
+extern crate x11_clipboard;
+
+use std::time::Duration;
+use x11_clipboard::xcb;
+use x11_clipboard::Clipboard;
+
+
+#[test]
+fn it_fall_back_to_cut_buffer() {
+    let clipboard = Clipboard::new().unwrap();
+    let connection = &clipboard.getter.connection;
+    let root = connection.get_setup().roots()
+        .nth(clipboard.getter.screen as usize)
+        .unwrap()
+        .root();
+
+    for &selection in &[clipboard.getter.atoms.clipboard, clipboard.getter.atoms.primary] {
+        xcb::set_selection_owner(connection, xcb::NONE, selection, xcb::CURRENT_TIME);
+    }
+    xcb::change_property(
+        connection, xcb::PROP_MODE_REPLACE as u8,
+        root, xcb::ATOM_CUT_BUFFER0, xcb::ATOM_STRING, 8,
+        b"caf\xe9"
+    );
+    connection.flush();
+
+    let text = clipboard.get_text_with_cutbuffer_fallback(Duration::from_secs(3)).unwrap();
+    assert_eq!(text, "café");
+
+    xcb::delete_property(connection, root, xcb::ATOM_CUT_BUFFER0);
+    connection.flush();
+}
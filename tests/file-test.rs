@@ -0,0 +1,46 @@
+extern crate x11_clipboard;
+
+use std::fs;
+use std::env;
+use std::time::Duration;
+use x11_clipboard::Clipboard;
+
+
+#[test]
+fn it_load_into_file() {
+    let data = (0..1_000_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+    let dest = env::temp_dir().join(format!("x11-clipboard-test-load-{}", std::process::id()));
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LOAD_FILE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, data.clone()).unwrap();
+    let written = clipboard.load_file(selection, atom_utf8string, atom_property, &dest, Duration::from_secs(10)).unwrap();
+    assert_eq!(written, data.len());
+
+    let output = fs::read(&dest).unwrap();
+    fs::remove_file(&dest).unwrap();
+    assert!(output == data);
+}
+
+#[cfg(feature = "mmap")]
+#[test]
+fn it_store_mapped_file() {
+    let data = (0..1_000_000u32).map(|i| (i % 241) as u8).collect::<Vec<_>>();
+    let path = env::temp_dir().join(format!("x11-clipboard-test-store-{}", std::process::id()));
+    fs::write(&path, &data).unwrap();
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_FILE").unwrap();
+    let atom_png = clipboard.setter.get_atom("image/png").unwrap();
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store_file(selection, atom_png, &path).unwrap();
+    let output = clipboard.load(selection, atom_png, atom_property, Duration::from_secs(10)).unwrap();
+    assert!(output == data);
+
+    drop(clipboard);
+    fs::remove_file(&path).unwrap();
+}
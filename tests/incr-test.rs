@@ -0,0 +1,423 @@
+extern crate x11_clipboard;
+
+use std::thread;
+use std::sync::{ Arc, Mutex };
+use std::sync::mpsc::channel;
+use std::time::{ Duration, Instant };
+use std::collections::HashMap;
+use x11_clipboard::xcb::{ self, Atom };
+use x11_clipboard::{ Clipboard, ClipboardBuilder, Context, LoadControl, MetricsSink };
+use x11_clipboard::error::Error;
+
+
+/// Own `selection` with a bare context and answer the first request by hand:
+/// `initial` is written as the INCR property value, then each chunk is written
+/// as `(type, bytes)` after the requestor deletes the previous one.
+fn incr_owner(name: &str, initial: Vec<u8>, chunks: Vec<(Atom, Vec<u8>)>) -> (Atom, thread::JoinHandle<()>) {
+    let chunks = chunks.into_iter()
+        .map(|(type_, chunk)| (type_, vec![chunk]))
+        .collect();
+    incr_owner_with_pieces(name, initial, chunks)
+}
+
+/// Like `incr_owner`, but each chunk is written in pieces:
+/// the first replaces the property, the rest are appended right after.
+fn incr_owner_with_pieces(name: &str, initial: Vec<u8>, chunks: Vec<(Atom, Vec<Vec<u8>>)>) -> (Atom, thread::JoinHandle<()>) {
+    let context = Context::new(None).unwrap();
+    let selection = context.get_atom(name).unwrap();
+
+    xcb::set_selection_owner(&context.connection, context.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&context.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), context.window);
+
+    let handle = thread::spawn(move || {
+        let (requestor, property) = loop {
+            let event = context.connection.wait_for_event().unwrap();
+            if event.response_type() & !0x80 != xcb::SELECTION_REQUEST { continue };
+            let event = unsafe { xcb::cast_event::<xcb::SelectionRequestEvent>(&event) };
+
+            xcb::change_window_attributes(
+                &context.connection, event.requestor(),
+                &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)]
+            );
+            xcb::change_property(
+                &context.connection, xcb::PROP_MODE_REPLACE as u8,
+                event.requestor(), event.property(), context.atoms.incr, 8,
+                &initial
+            );
+            xcb::send_event(
+                &context.connection, false, event.requestor(), 0,
+                &xcb::SelectionNotifyEvent::new(
+                    event.time(), event.requestor(), event.selection(),
+                    event.target(), event.property()
+                )
+            );
+            context.connection.flush();
+            break (event.requestor(), event.property());
+        };
+
+        for (type_, pieces) in chunks {
+            loop {
+                let event = context.connection.wait_for_event().unwrap();
+                if event.response_type() & !0x80 != xcb::PROPERTY_NOTIFY { continue };
+                let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+                if event.atom() == property && event.state() == xcb::PROPERTY_DELETE as u8 { break };
+            }
+
+            for (i, piece) in pieces.iter().enumerate() {
+                let mode = if i == 0 { xcb::PROP_MODE_REPLACE } else { xcb::PROP_MODE_APPEND };
+                xcb::change_property(
+                    &context.connection, mode as u8,
+                    requestor, property, type_, 8,
+                    piece
+                );
+            }
+            context.connection.flush();
+        }
+    });
+
+    (selection, handle)
+}
+
+#[test]
+fn it_work_with_data_in_incr_reply() {
+    let clipboard = Clipboard::new().unwrap();
+    let utf8_string = clipboard.getter.atoms.utf8_string;
+
+    let mut initial = (11i32).to_ne_bytes().to_vec();
+    initial.extend_from_slice(b"hello");
+    let (selection, handle) = incr_owner(
+        "X11_CLIPBOARD_TEST_INCR_DATA",
+        initial,
+        vec![(utf8_string, b" world".to_vec()), (utf8_string, Vec::new())]
+    );
+
+    let output = clipboard.load(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        Duration::from_secs(3)
+    ).unwrap();
+    assert_eq!(output, b"hello world");
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_end_incr_on_terminator_of_other_type() {
+    let clipboard = Clipboard::new().unwrap();
+    let utf8_string = clipboard.getter.atoms.utf8_string;
+
+    let (selection, handle) = incr_owner(
+        "X11_CLIPBOARD_TEST_INCR_TERMINATOR",
+        (4i32).to_ne_bytes().to_vec(),
+        vec![(utf8_string, b"data".to_vec()), (xcb::ATOM_INTEGER, Vec::new())]
+    );
+
+    let output = clipboard.load(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        Duration::from_secs(3)
+    ).unwrap();
+    assert_eq!(output, b"data");
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_read_chunk_appended_to_after_notify() {
+    let clipboard = Clipboard::new().unwrap();
+    let utf8_string = clipboard.getter.atoms.utf8_string;
+
+    let (selection, handle) = incr_owner_with_pieces(
+        "X11_CLIPBOARD_TEST_INCR_APPEND",
+        (11i32).to_ne_bytes().to_vec(),
+        vec![
+            (utf8_string, vec![b"hello".to_vec(), b" world".to_vec()]),
+            (utf8_string, vec![Vec::new()])
+        ]
+    );
+
+    let output = clipboard.load(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        Duration::from_secs(3)
+    ).unwrap();
+    assert_eq!(output, b"hello world");
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_report_load_progress() {
+    let clipboard = Clipboard::new().unwrap();
+    let utf8_string = clipboard.getter.atoms.utf8_string;
+
+    let (selection, handle) = incr_owner(
+        "X11_CLIPBOARD_TEST_INCR_PROGRESS",
+        (11i32).to_ne_bytes().to_vec(),
+        vec![(utf8_string, b"hello".to_vec()), (utf8_string, b" world".to_vec()), (utf8_string, Vec::new())]
+    );
+
+    let control = LoadControl::new();
+    let output = clipboard.load_controlled(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        &control, Duration::from_secs(3)
+    ).unwrap();
+    assert_eq!(output, b"hello world");
+    assert_eq!(control.size_hint(), Some(11));
+    assert_eq!(control.bytes_so_far(), 11);
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_cancel_load_mid_transfer() {
+    let clipboard = Clipboard::new().unwrap();
+    let utf8_string = clipboard.getter.atoms.utf8_string;
+
+    // the owner stops after the first chunk, so the load only ends by cancelling.
+    let (selection, handle) = incr_owner(
+        "X11_CLIPBOARD_TEST_INCR_CANCEL",
+        (11i32).to_ne_bytes().to_vec(),
+        vec![(utf8_string, b"hello".to_vec())]
+    );
+
+    let control = Arc::new(LoadControl::new());
+    let control2 = Arc::clone(&control);
+    let canceller = thread::spawn(move || {
+        while control2.bytes_so_far() < 5 {
+            thread::sleep(Duration::from_millis(10));
+        }
+        control2.cancel();
+    });
+
+    match clipboard.load_controlled(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        &control, Duration::from_secs(3)
+    ) {
+        Err(Error::Cancelled) => (),
+        other => panic!("expected cancel, got {:?}", other)
+    }
+
+    canceller.join().unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_read_size_hint_past_i32() {
+    let clipboard = Clipboard::new().unwrap();
+    let utf8_string = clipboard.getter.atoms.utf8_string;
+
+    // announced just below 4GB, far more than ever arrives.
+    let (selection, handle) = incr_owner(
+        "X11_CLIPBOARD_TEST_INCR_HUGE_HINT",
+        (0xffff_fff0u32).to_ne_bytes().to_vec(),
+        vec![(utf8_string, b"small".to_vec()), (utf8_string, Vec::new())]
+    );
+
+    let control = LoadControl::new();
+    let output = clipboard.load_controlled(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        &control, Duration::from_secs(3)
+    ).unwrap();
+    assert_eq!(output, b"small");
+    assert_eq!(control.size_hint(), Some(0xffff_fff0));
+
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_send_incr_in_configured_chunks() {
+    let data = (0..1_000_000u32).map(|i| (i % 251) as u8).collect::<Vec<_>>();
+
+    for &chunk_size in &[1000, usize::MAX] {
+        let clipboard = ClipboardBuilder::new().incr_chunk_size(chunk_size).build().unwrap();
+        let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_INCR_CHUNK_SIZE").unwrap();
+        let utf8_string = clipboard.setter.atoms.utf8_string;
+
+        clipboard.store(selection, utf8_string, data.clone()).unwrap();
+        let output = clipboard.load(
+            selection, utf8_string, clipboard.getter.atoms.property,
+            Duration::from_secs(10)
+        ).unwrap();
+        assert!(output == data, "chunk size {}", chunk_size);
+    }
+}
+
+#[test]
+fn it_report_store_progress() {
+    let data = vec![0x5a; 100_000];
+    let clipboard = ClipboardBuilder::new().incr_chunk_size(10_000).build().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_PROGRESS").unwrap();
+    let utf8_string = clipboard.setter.atoms.utf8_string;
+
+    let (sender, receiver) = channel();
+    clipboard.store_with_progress(selection, utf8_string, data.clone(), sender).unwrap();
+    let output = clipboard.load(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        Duration::from_secs(10)
+    ).unwrap();
+    assert!(output == data);
+
+    let progress = receiver.try_iter().collect::<Vec<_>>();
+    assert!(progress.len() >= 10, "{} chunks", progress.len());
+    assert!(progress.iter().all(|p| p.selection == selection && p.total == data.len()));
+    assert!(progress.windows(2).all(|w| w[0].sent < w[1].sent));
+    assert_eq!(progress.last().unwrap().sent, data.len());
+
+    // nobody listening anymore, pasting still works.
+    drop(receiver);
+    let output = clipboard.load(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        Duration::from_secs(10)
+    ).unwrap();
+    assert!(output == data);
+}
+
+#[test]
+fn it_stream_incr_chunks() {
+    let data = (0..1_000_000u32).map(|i| (i % 239) as u8).collect::<Vec<_>>();
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_INCR_STREAM").unwrap();
+    let utf8_string = clipboard.setter.atoms.utf8_string;
+    clipboard.store(selection, utf8_string, data.clone()).unwrap();
+
+    let stream = clipboard.load_stream(
+        selection, utf8_string, clipboard.getter.atoms.property,
+        Duration::from_secs(10)
+    ).unwrap();
+    let chunks = stream.collect::<Result<Vec<_>, _>>().unwrap();
+    assert!(chunks.len() > 1);
+    assert!(chunks.concat() == data);
+}
+
+#[test]
+fn it_round_trip_binary_values() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_BINARY").unwrap();
+    let target = clipboard.setter.get_atom("application/x-binary-test").unwrap();
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(10);
+
+    let small = vec![0u8, 255, 0, 128];
+    // well past the size a single request takes, so it goes through INCR.
+    let large = small.iter().cloned().cycle().take(1_000_002).collect::<Vec<_>>();
+
+    for data in &[small, large] {
+        clipboard.store(selection, target, data.clone()).unwrap();
+        assert!(clipboard.load(selection, target, atom_property, timeout).unwrap() == *data);
+
+        let mut targets = HashMap::new();
+        targets.insert(target, data.clone());
+        clipboard.store_multiple(selection, targets).unwrap();
+        assert!(clipboard.load(selection, target, atom_property, timeout).unwrap() == *data);
+    }
+}
+
+#[test]
+fn it_send_up_to_max_inline_size_directly() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_MAX_INLINE").unwrap();
+    let utf8_string = clipboard.setter.atoms.utf8_string;
+    let max = clipboard.max_inline_size();
+    assert!(max > 0 && max.is_multiple_of(4));
+
+    for &len in &[max, max + 1] {
+        let data = vec![b'x'; len];
+        clipboard.store(selection, utf8_string, data.clone()).unwrap();
+        let output = clipboard.load(
+            selection, utf8_string, clipboard.getter.atoms.property,
+            Duration::from_secs(10)
+        ).unwrap();
+        assert!(output == data, "length {}", len);
+    }
+}
+
+/// Records what's reported, one `(kind, selection, bytes, chunks)` per call.
+#[derive(Default)]
+struct Recorder(Mutex<Vec<(&'static str, Atom, usize, usize)>>);
+
+impl MetricsSink for Recorder {
+    fn on_store(&self, selection: Atom, bytes: usize) {
+        self.0.lock().unwrap().push(("store", selection, bytes, 0));
+    }
+
+    fn on_load(&self, selection: Atom, bytes: usize, _duration: Duration) {
+        self.0.lock().unwrap().push(("load", selection, bytes, 0));
+    }
+
+    fn on_incr_transfer(&self, selection: Atom, bytes: usize, chunks: usize) {
+        self.0.lock().unwrap().push(("incr", selection, bytes, chunks));
+    }
+}
+
+#[test]
+fn it_report_metrics() {
+    let recorder = Arc::new(Recorder::default());
+    let clipboard = ClipboardBuilder::new()
+        .incr_chunk_size(4096)
+        .metrics(recorder.clone())
+        .build()
+        .unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_METRICS").unwrap();
+    let utf8_string = clipboard.setter.atoms.utf8_string;
+    let len = clipboard.max_inline_size() + 1;
+
+    clipboard.store(selection, utf8_string, vec![b'x'; len]).unwrap();
+    let output = clipboard.load(selection, utf8_string, clipboard.getter.atoms.property, Duration::from_secs(10)).unwrap();
+    assert_eq!(output.len(), len);
+
+    // the worker reports the transfer once it sent the terminator, maybe after the load ended.
+    let start = Instant::now();
+    while !recorder.0.lock().unwrap().iter().any(|record| record.0 == "incr") {
+        assert!(start.elapsed() < Duration::from_secs(3));
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let records = recorder.0.lock().unwrap();
+    assert!(records.contains(&("store", selection, len, 0)));
+    assert!(records.contains(&("load", selection, len, 0)));
+    let chunks = len.div_ceil(4096);
+    assert!(records.contains(&("incr", selection, len, chunks)));
+}
+
+#[test]
+fn it_replace_target_mid_transfer() {
+    let clipboard = ClipboardBuilder::new().incr_chunk_size(4096).build().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_INCR_REPLACE").unwrap();
+    let utf8_string = clipboard.setter.atoms.utf8_string;
+    let len = clipboard.max_inline_size() + 1;
+    clipboard.store(selection, utf8_string, vec![b'x'; len]).unwrap();
+
+    // read the first chunk by hand, so the transfer is known to be under way.
+    let requestor = Context::new(None).unwrap();
+    let property = requestor.atoms.property;
+    xcb::convert_selection(&requestor.connection, requestor.window, selection, utf8_string, property, xcb::CURRENT_TIME);
+    requestor.connection.flush();
+    let mut chunks = 0;
+    while chunks < 2 {
+        let event = requestor.connection.wait_for_event().unwrap();
+        let is_new = match event.response_type() & !0x80 {
+            xcb::SELECTION_NOTIFY => true,
+            xcb::PROPERTY_NOTIFY => {
+                let event = unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) };
+                event.atom() == property && event.state() == xcb::PROPERTY_NEW_VALUE as u8
+            },
+            _ => false
+        };
+        if !is_new { continue };
+        // the INCR header, then the first chunk.
+        xcb::get_property(&requestor.connection, true, requestor.window, property, xcb::ATOM_ANY, 0, u32::MAX)
+            .get_reply()
+            .unwrap();
+        chunks += 1;
+    }
+
+    // shorter than what was sent already, the transfer is dropped rather than read past the end.
+    clipboard.add_target(selection, utf8_string, "short").unwrap();
+    xcb::delete_property(&requestor.connection, requestor.window, property);
+    requestor.connection.flush();
+    thread::sleep(Duration::from_millis(200));
+
+    assert!(clipboard.is_alive());
+    let output = clipboard.load(selection, utf8_string, clipboard.getter.atoms.property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"short");
+}
@@ -0,0 +1,45 @@
+extern crate x11_clipboard;
+
+use std::thread;
+use std::time::Duration;
+use x11_clipboard::xcb;
+use x11_clipboard::{ ClipboardBuilder, Context, Inspector };
+
+
+#[test]
+fn it_hand_clipboard_to_manager_on_drop() {
+    // a stand-in clipboard manager, copying `UTF8_STRING` when asked to save.
+    let manager = Context::new(None).unwrap();
+    let atoms = manager.atoms.clone();
+    xcb::set_selection_owner(&manager.connection, manager.window, atoms.clipboard_manager, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&manager.connection, atoms.clipboard_manager).get_reply().unwrap();
+    assert_eq!(owner.owner(), manager.window);
+
+    let handle = thread::spawn(move || {
+        loop {
+            let event = manager.connection.wait_for_event().unwrap();
+            if event.response_type() & !0x80 != xcb::SELECTION_REQUEST { continue };
+            let event = unsafe { xcb::cast_event::<xcb::SelectionRequestEvent>(&event) };
+            if event.target() != atoms.save_targets { continue };
+
+            let inspector = Inspector::new().unwrap();
+            let saved = inspector.load(atoms.clipboard, atoms.utf8_string, atoms.property, Duration::from_secs(3)).unwrap();
+
+            xcb::send_event(
+                &manager.connection, false, event.requestor(), 0,
+                &xcb::SelectionNotifyEvent::new(
+                    event.time(), event.requestor(), event.selection(),
+                    event.target(), event.property()
+                )
+            );
+            manager.connection.flush();
+            break saved;
+        }
+    });
+
+    let clipboard = ClipboardBuilder::new().persist_on_exit(Duration::from_secs(3)).build().unwrap();
+    clipboard.store(clipboard.setter.atoms.clipboard, clipboard.setter.atoms.utf8_string, "kept").unwrap();
+    drop(clipboard);
+
+    assert_eq!(handle.join().unwrap(), b"kept");
+}
@@ -0,0 +1,21 @@
+extern crate x11_clipboard;
+
+use std::time::Duration;
+use x11_clipboard::Clipboard;
+
+
+#[test]
+fn it_store_and_load_primary_and_clipboard() {
+    let clipboard = Clipboard::new().unwrap();
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store_primary("selected").unwrap();
+    clipboard.store_clipboard("copied").unwrap();
+
+    assert_eq!(clipboard.load_primary(timeout).unwrap(), "selected");
+    assert_eq!(clipboard.load_clipboard(timeout).unwrap(), "copied");
+
+    // the two are independent of each other.
+    clipboard.relinquish_primary().unwrap();
+    assert_eq!(clipboard.load_clipboard(timeout).unwrap(), "copied");
+}
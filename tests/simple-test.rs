@@ -1,7 +1,18 @@
 extern crate x11_clipboard;
 
 use std::time::{ Instant, Duration };
-use x11_clipboard::Clipboard;
+use std::fs::File;
+use std::mem::ManuallyDrop;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::io::FromRawFd;
+use std::thread;
+use std::panic::{ self, AssertUnwindSafe };
+use std::sync::Arc;
+use std::sync::mpsc::{ channel, RecvTimeoutError };
+use std::collections::{ HashMap, HashSet };
+use x11_clipboard::{ Clipboard, ClipboardBuilder, Context, Inspector, ByteOrder, LoadOutcome, RequestorPolicy, CancelToken, Deadline, WatchEvent, INCR_CHUNK_SIZE, parse_atoms_with_order };
+use x11_clipboard::error::Error;
+use x11_clipboard::xcb;
 
 
 #[test]
@@ -31,3 +42,1772 @@ fn it_work() {
     let output = clipboard.load(atom_clipboard, atom_utf8string, atom_property, dur).unwrap();
     assert_eq!(output, data.as_bytes());
 }
+
+#[test]
+fn it_resolve_available_target() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_RESOLVE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let dur = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "resolve").unwrap();
+
+    let available = clipboard.resolve_available_target(selection, "UTF8_STRING", dur).unwrap();
+    assert_eq!(available, Some(atom_utf8string));
+
+    let unavailable = clipboard.resolve_available_target(selection, "STRING", dur).unwrap();
+    assert_eq!(unavailable, None);
+
+    let name = format!("X11_CLIPBOARD_TEST_NEVER_INTERNED_{:?}", Instant::now());
+    let unknown = clipboard.resolve_available_target(selection, &name, dur).unwrap();
+    assert_eq!(unknown, None);
+}
+
+#[test]
+fn it_serve_ephemeral_once() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_EPHEMERAL").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.setter.atoms.property;
+    let dur = Duration::from_secs(3);
+
+    clipboard.store_ephemeral(selection, atom_utf8string, "secret").unwrap();
+
+    let output = clipboard.load(selection, atom_utf8string, atom_property, dur).unwrap();
+    assert_eq!(output, b"secret");
+
+    match clipboard.load(selection, atom_utf8string, atom_property, dur) {
+        Err(Error::NoOwner) => (),
+        other => panic!("expected no owner, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_load_without_owner() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_NO_OWNER").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, "gone soon").unwrap();
+    clipboard.clear_sync(selection).unwrap();
+
+    // no timeout, it must not wait for an owner that isn't there.
+    match clipboard.load(selection, atom_utf8string, atom_property, None) {
+        Err(Error::NoOwner) => (),
+        other => panic!("expected no owner, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_snapshot_all_targets() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_SNAPSHOT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+
+    let mut targets = HashMap::new();
+    targets.insert(atom_utf8string, "snapshot");
+    targets.insert(atom_string, "snapshot");
+    targets.insert(atom_html, "<b>snapshot</b>");
+    clipboard.store_multiple(selection, targets).unwrap();
+
+    let mut snapshot = clipboard.snapshot(selection, Duration::from_secs(3)).unwrap();
+    snapshot.sort();
+
+    let mut expected = vec![
+        (atom_utf8string, b"snapshot".to_vec()),
+        (atom_string, b"snapshot".to_vec()),
+        (atom_html, b"<b>snapshot</b>".to_vec())
+    ];
+    expected.sort();
+    assert_eq!(snapshot, expected);
+}
+
+#[test]
+fn it_add_target_to_stored() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_ADD_TARGET").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "added").unwrap();
+    let fingerprint = clipboard.content_fingerprint(selection);
+    clipboard.add_target(selection, atom_html, "<i>added</i>").unwrap();
+    assert_ne!(clipboard.content_fingerprint(selection), fingerprint);
+
+    let targets = clipboard.load_target_atoms(selection, timeout).unwrap();
+    assert!(targets.contains(&atom_utf8string));
+    assert!(targets.contains(&atom_html));
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"added");
+    assert_eq!(clipboard.load(selection, atom_html, atom_property, timeout).unwrap(), b"<i>added</i>");
+
+    // adding it again replaces the value rather than listing it twice.
+    clipboard.add_target(selection, atom_html, "<b>added</b>").unwrap();
+    let targets = clipboard.load_target_atoms(selection, timeout).unwrap();
+    assert_eq!(targets.iter().filter(|&&target| target == atom_html).count(), 1);
+    assert_eq!(clipboard.load(selection, atom_html, atom_property, timeout).unwrap(), b"<b>added</b>");
+}
+
+#[test]
+fn it_remove_target_from_stored() {
+    let clipboard = Clipboard::new().unwrap();
+    let inspector = Inspector::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_REMOVE_TARGET").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    let mut targets = HashMap::new();
+    targets.insert(atom_utf8string, "kept");
+    targets.insert(atom_string, "kept");
+    clipboard.store_multiple(selection, targets).unwrap();
+
+    clipboard.remove_target(selection, atom_string).unwrap();
+    let targets = clipboard.load_target_atoms(selection, timeout).unwrap();
+    assert!(targets.contains(&atom_utf8string));
+    assert!(!targets.contains(&atom_string));
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"kept");
+    match clipboard.load_status(selection, atom_string, atom_property, timeout) {
+        Ok(LoadOutcome::Refused) => (),
+        other => panic!("expected refusal, got {:?}", other)
+    }
+
+    // the last one goes with the selection.
+    clipboard.remove_target(selection, atom_utf8string).unwrap();
+    assert_eq!(inspector.owner(selection).unwrap(), xcb::NONE);
+}
+
+#[test]
+fn it_delete_on_request() {
+    let clipboard = Clipboard::new().unwrap();
+    let inspector = Inspector::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DELETE").unwrap();
+    let atom_delete = clipboard.getter.atoms.delete;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, clipboard.setter.atoms.utf8_string, "deleted").unwrap();
+    assert_eq!(inspector.owner(selection).unwrap(), clipboard.setter.window);
+
+    // answered with an empty `NULL`, the selection given up before that.
+    match clipboard.load_typed(selection, atom_delete, atom_property, timeout) {
+        Ok((type_, data)) => {
+            assert_eq!(type_, clipboard.getter.atoms.null);
+            assert!(data.is_empty());
+        },
+        other => panic!("expected empty NULL, got {:?}", other)
+    }
+    assert_eq!(inspector.owner(selection).unwrap(), xcb::NONE);
+    assert_eq!(clipboard.content_fingerprint(selection), None);
+
+    // `NULL` is what `DELETE` is expected to come as.
+    clipboard.store(selection, clipboard.setter.atoms.utf8_string, "deleted").unwrap();
+    assert_eq!(clipboard.load(selection, atom_delete, atom_property, timeout).unwrap(), b"");
+    assert_eq!(inspector.owner(selection).unwrap(), xcb::NONE);
+}
+
+#[test]
+fn it_delete_within_multiple() {
+    let clipboard = Clipboard::new().unwrap();
+    let inspector = Inspector::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_DELETE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_delete = clipboard.setter.atoms.delete;
+
+    clipboard.store(selection, atom_utf8string, "moved").unwrap();
+
+    // a move: the value, then `DELETE`, in one request.
+    let context = Context::new(None).unwrap();
+    let atom_pair = context.get_atom("ATOM_PAIR").unwrap();
+    let atom_pairs = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_DELETE_PAIRS").unwrap();
+    let atom_a = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_DELETE_A").unwrap();
+    let atom_b = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_DELETE_B").unwrap();
+
+    xcb::change_property(
+        &context.connection, xcb::PROP_MODE_REPLACE as u8, context.window, atom_pairs, atom_pair, 32,
+        &[atom_utf8string, atom_a, atom_delete, atom_b]
+    );
+    xcb::convert_selection(&context.connection, context.window, selection, context.atoms.multiple, atom_pairs, xcb::CURRENT_TIME);
+    context.connection.flush();
+
+    let property = loop {
+        let event = context.connection.wait_for_event().unwrap();
+        if event.response_type() & !0x80 == xcb::SELECTION_NOTIFY {
+            break unsafe { xcb::cast_event::<xcb::SelectionNotifyEvent>(&event) }.property();
+        }
+    };
+    assert_eq!(property, atom_pairs);
+
+    let get = |property| xcb::get_property(&context.connection, false, context.window, property, xcb::ATOM_ANY, 0, 1024)
+        .get_reply()
+        .unwrap();
+    assert_eq!(get(atom_pairs).value::<u32>(), &[atom_utf8string, atom_a, atom_delete, atom_b]);
+    assert_eq!(get(atom_a).value::<u8>(), b"moved");
+    let deleted = get(atom_b);
+    assert_eq!(deleted.type_(), context.atoms.null);
+    assert_eq!(deleted.value_len(), 0);
+    assert_eq!(inspector.owner(selection).unwrap(), xcb::NONE);
+}
+
+#[test]
+fn it_snapshot_without_deleting() {
+    let clipboard = Clipboard::new().unwrap();
+    let inspector = Inspector::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_SNAPSHOT_DELETE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    // listed in `TARGETS`, the way some owners do.
+    let mut targets = HashMap::new();
+    targets.insert(atom_utf8string, "kept");
+    targets.insert(clipboard.setter.atoms.delete, "");
+    clipboard.store_multiple(selection, targets).unwrap();
+
+    let snapshot = clipboard.snapshot(selection, Duration::from_secs(3)).unwrap();
+    assert_eq!(snapshot, vec![(atom_utf8string, b"kept".to_vec())]);
+    assert_eq!(inspector.owner(selection).unwrap(), clipboard.setter.window);
+}
+
+#[test]
+fn it_list_owned_selections() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_OWNED").unwrap();
+    let other = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_OWNED_OTHER").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+
+    assert!(clipboard.owned_selections().unwrap().is_empty());
+
+    let mut targets = HashMap::new();
+    targets.insert(atom_utf8string, "owned");
+    targets.insert(atom_html, "<i>owned</i>");
+    clipboard.store_multiple(selection, targets).unwrap();
+    clipboard.store(other, atom_utf8string, "other").unwrap();
+
+    let owned = clipboard.owned_selections().unwrap().into_iter().collect::<HashSet<_>>();
+    assert_eq!(owned, [selection, other].iter().cloned().collect());
+    let stored = clipboard.owned_targets(selection).unwrap().into_iter().collect::<HashSet<_>>();
+    assert_eq!(stored, [atom_utf8string, atom_html].iter().cloned().collect());
+
+    clipboard.clear(other).unwrap();
+    assert_eq!(clipboard.owned_selections().unwrap(), vec![selection]);
+    assert!(clipboard.owned_targets(other).unwrap().is_empty());
+}
+
+#[test]
+fn it_reuse_pooled_properties() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_POOL").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    clipboard.store(selection, atom_utf8string, "pooled").unwrap();
+
+    for _ in 0..20 {
+        let output = clipboard.load_pooled(selection, atom_utf8string, Duration::from_secs(3)).unwrap();
+        assert_eq!(output, b"pooled");
+    }
+
+    let exists = |name| x11_clipboard::xcb::intern_atom(&clipboard.getter.connection, true, name)
+        .get_reply()
+        .unwrap()
+        .atom() != x11_clipboard::xcb::ATOM_NONE;
+    assert!(exists("THIS_CLIPBOARD_OUT_0"));
+    assert!(!exists("THIS_CLIPBOARD_OUT_1"));
+}
+
+#[test]
+fn it_parse_atoms_with_order() {
+    let big = [0, 0, 0, 1, 0, 0, 1, 0];
+    let little = [1, 0, 0, 0, 0, 1, 0, 0];
+
+    assert_eq!(parse_atoms_with_order(&big, ByteOrder::Big), vec![1, 256]);
+    assert_eq!(parse_atoms_with_order(&little, ByteOrder::Little), vec![1, 256]);
+
+    let swapped = match ByteOrder::native() {
+        ByteOrder::Little => ByteOrder::Big,
+        ByteOrder::Big => ByteOrder::Little
+    };
+    let native = parse_atoms_with_order(&little, ByteOrder::Little);
+    assert_ne!(parse_atoms_with_order(&little, swapped), native);
+}
+
+#[test]
+fn it_inspect_without_owning() {
+    let clipboard = Clipboard::new().unwrap();
+    let inspector = Inspector::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_INSPECT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let dur = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "inspected").unwrap();
+
+    assert_eq!(inspector.owner(selection).unwrap(), clipboard.setter.window);
+    assert_eq!(inspector.target_names(selection, dur).unwrap(), vec!["TARGETS", "MULTIPLE", "TIMESTAMP", "LENGTH", "UTF8_STRING"]);
+    assert!(inspector.load_target_atoms(selection, dur).unwrap().contains(&atom_utf8string));
+
+    let output = inspector.load(selection, atom_utf8string, inspector.context.atoms.property, dur).unwrap();
+    assert_eq!(output, b"inspected");
+}
+
+#[test]
+fn it_serve_deferred_value() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DEFERRED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.setter.atoms.property;
+    let dur = Duration::from_secs(3);
+
+    clipboard.store_deferred(selection, atom_utf8string, || {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            sender.send(b"rendered".to_vec()).unwrap();
+        });
+        receiver
+    }).unwrap();
+
+    let output = clipboard.load(selection, atom_utf8string, atom_property, dur).unwrap();
+    assert_eq!(output, b"rendered");
+
+    let output = clipboard.load(selection, atom_utf8string, atom_property, dur).unwrap();
+    assert_eq!(output, b"rendered");
+}
+
+#[test]
+fn it_serve_legacy_text_target() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LEGACY").unwrap();
+    let atom_compound_text = clipboard.setter.get_atom("COMPOUND_TEXT").unwrap();
+    let atom_property = clipboard.setter.atoms.property;
+
+    clipboard.store(selection, clipboard.setter.atoms.string, "legacy").unwrap();
+
+    let output = clipboard.load(selection, atom_compound_text, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"legacy");
+}
+
+#[test]
+fn it_timeout_at_default() {
+    let mut clipboard = Clipboard::new().unwrap();
+    let silent = Context::new(None).unwrap();
+    let selection = silent.get_atom("X11_CLIPBOARD_TEST_DEFAULT_TIMEOUT").unwrap();
+
+    // owns the selection but never answers
+    xcb::set_selection_owner(&silent.connection, silent.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&silent.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), silent.window);
+
+    clipboard.set_default_timeout(Some(Duration::from_millis(200)));
+    let start = Instant::now();
+    match clipboard.load_default(selection, clipboard.getter.atoms.utf8_string, clipboard.getter.atoms.property) {
+        Err(Error::Timeout(info)) => {
+            assert_eq!(info.selection, selection);
+            assert_eq!(info.target, clipboard.getter.atoms.utf8_string);
+            assert!(info.elapsed >= Duration::from_millis(200));
+        },
+        other => panic!("expected timeout, got {:?}", other)
+    }
+    assert!(start.elapsed() < Duration::from_secs(3));
+}
+
+#[test]
+fn it_fail_load_on_lost_connection() {
+    let loader = Inspector::new().unwrap();
+    let silent = Context::new(None).unwrap();
+    let selection = silent.get_atom("X11_CLIPBOARD_TEST_LOST_CONNECTION").unwrap();
+
+    xcb::set_selection_owner(&silent.connection, silent.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&silent.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), silent.window);
+
+    // another client kills the loader's connection while it waits on the silent owner.
+    let loader_window = loader.context.window;
+    let killer = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        let context = Context::new(None).unwrap();
+        xcb::kill_client(&context.connection, loader_window);
+        context.connection.flush();
+    });
+
+    // waiting on regardless would only end with the timeout.
+    let start = Instant::now();
+    match loader.load(selection, silent.atoms.utf8_string, silent.atoms.property, Duration::from_secs(10)) {
+        Err(Error::XcbConn(_)) => (),
+        other => panic!("expected connection error, got {:?}", other)
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+
+    killer.join().unwrap();
+}
+
+#[test]
+fn it_share_deadline_between_loads() {
+    let clipboard = Clipboard::new().unwrap();
+    let silent = Context::new(None).unwrap();
+    let selection = silent.get_atom("X11_CLIPBOARD_TEST_DEADLINE").unwrap();
+    let atom_utf8string = clipboard.getter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    xcb::set_selection_owner(&silent.connection, silent.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&silent.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), silent.window);
+
+    let start = Instant::now();
+    let deadline = start + Duration::from_millis(200);
+    for _ in 0..3 {
+        match clipboard.load_until(selection, atom_utf8string, atom_property, deadline) {
+            Err(Error::Timeout(_)) => (),
+            other => panic!("expected timeout, got {:?}", other)
+        }
+    }
+    assert!(start.elapsed() < Duration::from_millis(500));
+
+    let other = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DEADLINE_DATA").unwrap();
+    clipboard.store(other, atom_utf8string, "in time").unwrap();
+    let deadline = Deadline::after(Some(Duration::from_secs(3)));
+    assert_eq!(clipboard.load_until(other, atom_utf8string, atom_property, deadline).unwrap(), b"in time");
+}
+
+#[test]
+fn it_cancel_pending_load() {
+    let clipboard = Clipboard::new().unwrap();
+    let silent = Context::new(None).unwrap();
+    let selection = silent.get_atom("X11_CLIPBOARD_TEST_LOAD_CANCEL").unwrap();
+
+    // owns the selection but never answers, without a timeout the load would never end.
+    xcb::set_selection_owner(&silent.connection, silent.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&silent.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), silent.window);
+
+    let handle = Arc::new(clipboard.load_cancellable(selection, clipboard.getter.atoms.utf8_string, clipboard.getter.atoms.property, None));
+    let start = Instant::now();
+    let canceller = {
+        let handle = Arc::clone(&handle);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            handle.cancel();
+        })
+    };
+    canceller.join().unwrap();
+
+    let handle = Arc::try_unwrap(handle).ok().unwrap();
+    match handle.wait() {
+        Err(Error::Cancelled) => (),
+        other => panic!("expected cancel, got {:?}", other)
+    }
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn it_expose_connection_fd() {
+    let context = Context::new(None).unwrap();
+    let other = Context::new(None).unwrap();
+    assert!(context.raw_fd() >= 0);
+    assert_ne!(context.raw_fd(), other.raw_fd());
+
+    // borrowed only, the connection still owns it.
+    let file = ManuallyDrop::new(unsafe { File::from_raw_fd(context.raw_fd()) });
+    assert!(file.metadata().unwrap().file_type().is_socket());
+    context.get_atom("X11_CLIPBOARD_TEST_RAW_FD").unwrap();
+}
+
+#[test]
+fn it_select_extra_event_mask() {
+    let required = xcb::EVENT_MASK_STRUCTURE_NOTIFY | xcb::EVENT_MASK_PROPERTY_CHANGE;
+
+    let context = Context::new_with_event_mask(None, xcb::EVENT_MASK_FOCUS_CHANGE).unwrap();
+    let attributes = xcb::get_window_attributes(&context.connection, context.window).get_reply().unwrap();
+    assert_eq!(attributes.your_event_mask(), required | xcb::EVENT_MASK_FOCUS_CHANGE);
+
+    let clipboard = ClipboardBuilder::new().event_mask(xcb::EVENT_MASK_FOCUS_CHANGE).build().unwrap();
+    let attributes = xcb::get_window_attributes(&clipboard.getter.connection, clipboard.getter.window).get_reply().unwrap();
+    assert_eq!(attributes.your_event_mask(), required | xcb::EVENT_MASK_FOCUS_CHANGE);
+
+    // nothing can take away what loads need.
+    let context = Context::new_with_event_mask(None, xcb::EVENT_MASK_NO_EVENT).unwrap();
+    let attributes = xcb::get_window_attributes(&context.connection, context.window).get_reply().unwrap();
+    assert_eq!(attributes.your_event_mask(), required);
+}
+
+#[test]
+fn it_load_on_own_window() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_ON_WINDOW").unwrap();
+    let atom_utf8string = clipboard.getter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    // a window of the caller's, on the getter's connection, watching its properties for INCR.
+    let connection = &clipboard.getter.connection;
+    let screen = connection.get_setup().roots().nth(clipboard.getter.screen as usize).unwrap();
+    let window = connection.generate_id();
+    xcb::create_window(
+        connection,
+        xcb::COPY_FROM_PARENT as u8,
+        window, screen.root(),
+        0, 0, 1, 1,
+        0,
+        xcb::WINDOW_CLASS_INPUT_OUTPUT as u16,
+        screen.root_visual(),
+        &[(xcb::CW_EVENT_MASK, xcb::EVENT_MASK_PROPERTY_CHANGE)]
+    );
+    connection.flush();
+
+    clipboard.store(selection, atom_utf8string, "small").unwrap();
+    let output = clipboard.load_on_window(window, selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"small");
+
+    let data = vec![b'x'; clipboard.max_inline_size() + 1];
+    clipboard.store(selection, atom_utf8string, data.clone()).unwrap();
+    let output = clipboard.load_on_window(window, selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, data);
+
+    // nothing is left behind on the window.
+    let reply = xcb::get_property(connection, false, window, atom_property, xcb::ATOM_ANY, 0, 0).get_reply().unwrap();
+    assert_eq!(reply.type_(), xcb::ATOM_NONE);
+
+    xcb::destroy_window(connection, window);
+    connection.flush();
+}
+
+#[test]
+fn it_restore_after_reconnect() {
+    let mut clipboard = ClipboardBuilder::new()
+        .auto_restore_on_reconnect(true)
+        .build()
+        .unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_RECONNECT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    clipboard.store(selection, atom_utf8string, "restored").unwrap();
+    let old_window = clipboard.setter.window;
+
+    clipboard.reconnect().unwrap();
+    assert_ne!(clipboard.setter.window, old_window);
+
+    let owner = xcb::get_selection_owner(&clipboard.getter.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), clipboard.setter.window);
+
+    let output = clipboard.load(selection, atom_utf8string, clipboard.getter.atoms.property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"restored");
+}
+
+#[test]
+fn it_fingerprint_stored_content() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_FINGERPRINT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    assert_eq!(clipboard.content_fingerprint(selection), None);
+
+    clipboard.store(selection, atom_utf8string, "same").unwrap();
+    let first = clipboard.content_fingerprint(selection).unwrap();
+
+    clipboard.store(selection, atom_utf8string, "same").unwrap();
+    assert_eq!(clipboard.content_fingerprint(selection), Some(first));
+
+    clipboard.store(selection, atom_utf8string, "other").unwrap();
+    assert_ne!(clipboard.content_fingerprint(selection), Some(first));
+}
+
+#[test]
+fn it_serve_derived_targets() {
+    fn utf8(source: &[u8]) -> Vec<u8> {
+        source.to_vec()
+    }
+
+    fn latin1(source: &[u8]) -> Vec<u8> {
+        String::from_utf8_lossy(source).chars()
+            .map(|c| if (c as u32) < 256 { c as u8 } else { b'?' })
+            .collect()
+    }
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DERIVED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store_derived(
+        selection,
+        "café €".as_bytes().to_vec(),
+        vec![(atom_utf8string, utf8), (atom_string, latin1)]
+    ).unwrap();
+
+    let output = clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, "café €".as_bytes());
+
+    let output = clipboard.load(selection, atom_string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"caf\xe9 ?");
+}
+
+#[test]
+fn it_load_status() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LOAD_STATUS").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    assert_eq!(
+        clipboard.load_status(selection, atom_utf8string, atom_property, timeout).unwrap(),
+        LoadOutcome::NoOwner
+    );
+
+    clipboard.store(selection, atom_utf8string, "data").unwrap();
+    assert_eq!(
+        clipboard.load_status(selection, atom_utf8string, atom_property, timeout).unwrap(),
+        LoadOutcome::Data(b"data".to_vec())
+    );
+    assert_eq!(
+        clipboard.load_status(selection, atom_string, atom_property, timeout).unwrap(),
+        LoadOutcome::Refused
+    );
+
+    clipboard.store(selection, atom_utf8string, "").unwrap();
+    assert_eq!(
+        clipboard.load_status(selection, atom_utf8string, atom_property, timeout).unwrap(),
+        LoadOutcome::Empty
+    );
+}
+
+#[test]
+fn it_refuse_denied_requestor() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_REQUESTOR_POLICY").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "secret").unwrap();
+
+    let mut denied = HashSet::new();
+    denied.insert(clipboard.getter.window);
+    clipboard.set_requestor_policy(RequestorPolicy::Deny(denied));
+    assert_eq!(
+        clipboard.load_status(selection, atom_utf8string, atom_property, timeout).unwrap(),
+        LoadOutcome::Refused
+    );
+
+    let other = Inspector::new().unwrap();
+    assert_eq!(other.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"secret");
+
+    clipboard.set_requestor_policy(RequestorPolicy::AllowAll);
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"secret");
+}
+
+#[test]
+fn it_check_multiple_support() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_SUPPORTS_MULTIPLE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let timeout = Duration::from_secs(3);
+
+    assert!(!clipboard.supports_multiple(selection, timeout).unwrap());
+
+    clipboard.store(selection, atom_utf8string, "data").unwrap();
+    assert!(clipboard.supports_multiple(selection, timeout).unwrap());
+}
+
+#[test]
+fn it_answer_multiple() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_MULTIPLE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+
+    let mut targets = HashMap::new();
+    targets.insert(atom_utf8string, "utf8");
+    targets.insert(atom_string, "latin1");
+    clipboard.store_multiple(selection, targets).unwrap();
+
+    let context = Context::new(None).unwrap();
+    let atom_pair = context.get_atom("ATOM_PAIR").unwrap();
+    let atom_pairs = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_PAIRS").unwrap();
+    let atom_a = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_A").unwrap();
+    let atom_b = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_B").unwrap();
+    let atom_c = context.get_atom("X11_CLIPBOARD_TEST_MULTIPLE_C").unwrap();
+
+    xcb::change_property(
+        &context.connection, xcb::PROP_MODE_REPLACE as u8, context.window, atom_pairs, atom_pair, 32,
+        &[atom_utf8string, atom_a, atom_html, atom_b, atom_string, atom_c]
+    );
+    xcb::convert_selection(&context.connection, context.window, selection, context.atoms.multiple, atom_pairs, xcb::CURRENT_TIME);
+    context.connection.flush();
+
+    let property = loop {
+        let event = context.connection.wait_for_event().unwrap();
+        if event.response_type() & !0x80 == xcb::SELECTION_NOTIFY {
+            break unsafe { xcb::cast_event::<xcb::SelectionNotifyEvent>(&event) }.property();
+        }
+    };
+    assert_eq!(property, atom_pairs);
+
+    let get = |property| xcb::get_property(&context.connection, false, context.window, property, xcb::ATOM_ANY, 0, 1024)
+        .get_reply()
+        .unwrap();
+    assert_eq!(get(atom_pairs).value::<u32>(), &[atom_utf8string, atom_a, atom_html, xcb::ATOM_NONE, atom_string, atom_c]);
+    assert_eq!(get(atom_a).value::<u8>(), b"utf8");
+    assert_eq!(get(atom_c).value::<u8>(), b"latin1");
+}
+
+#[test]
+fn it_force_store_over_other_owner() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_FORCE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let other = Context::new(None).unwrap();
+    xcb::set_selection_owner(&other.connection, other.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&other.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), other.window);
+
+    clipboard.store_force(selection, atom_utf8string, "forced").unwrap();
+    let owner = xcb::get_selection_owner(&other.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), clipboard.setter.window);
+
+    let output = clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"forced");
+}
+
+#[test]
+fn it_report_setter_exit() {
+    let (sender, receiver) = channel();
+    let clipboard = ClipboardBuilder::new()
+        .on_setter_exit(move |error| sender.send(error.is_none()).unwrap())
+        .build()
+        .unwrap();
+
+    drop(clipboard);
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(3)), Ok(true));
+}
+
+#[test]
+fn it_normalize_line_endings() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_NORMALIZED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "one\r\ntwo\rthree\nfour\r\r\n").unwrap();
+    assert_eq!(clipboard.get_text_normalized(selection, timeout).unwrap(), "one\ntwo\nthree\nfour\n\n");
+
+    clipboard.store(selection, atom_string, &b"caf\xe9\r\n"[..]).unwrap();
+    assert_eq!(clipboard.get_text_normalized(selection, timeout).unwrap(), "café\n");
+}
+
+#[test]
+fn it_store_atoms() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_ATOMS").unwrap();
+    let target = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_ATOM_LIST").unwrap();
+    let atoms = vec![clipboard.setter.atoms.utf8_string, clipboard.setter.atoms.string, target];
+
+    clipboard.store_atoms(selection, target, atoms.clone()).unwrap();
+    assert_eq!(clipboard.load_atoms(selection, target, Duration::from_secs(3)).unwrap(), atoms);
+}
+
+#[test]
+fn it_wait_for_ownership_loss() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_OWNERSHIP_LOSS").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    clipboard.store(selection, atom_utf8string, "first").unwrap();
+    match clipboard.wait_for_ownership_loss(selection, Duration::from_millis(100)) {
+        Err(Error::Timeout(_)) => (),
+        other => panic!("expected timeout, got {:?}", other)
+    }
+
+    let handle = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        let other = Clipboard::new().unwrap();
+        other.store(selection, atom_utf8string, "second").unwrap();
+        other
+    });
+
+    clipboard.wait_for_ownership_loss(selection, Duration::from_secs(3)).unwrap();
+    handle.join().unwrap();
+}
+
+#[test]
+fn it_relinquish_on_drop() {
+    let observer = Context::new(None).unwrap();
+    let selection = observer.get_atom("X11_CLIPBOARD_TEST_DROP_RELINQUISH").unwrap();
+
+    let clipboard = Clipboard::new().unwrap();
+    clipboard.store(selection, clipboard.setter.atoms.utf8_string, "dropped").unwrap();
+    let owner = xcb::get_selection_owner(&observer.connection, selection).get_reply().unwrap();
+    assert_ne!(owner.owner(), xcb::NONE);
+    drop(clipboard);
+
+    let start = Instant::now();
+    loop {
+        let owner = xcb::get_selection_owner(&observer.connection, selection).get_reply().unwrap();
+        if owner.owner() == xcb::NONE { break };
+        assert!(start.elapsed() < Duration::from_secs(3), "selection still owned after drop");
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[test]
+fn it_cleanup_stale_properties() {
+    let context = Context::new(None).unwrap();
+    let pooled = context.get_atom("THIS_CLIPBOARD_OUT_0").unwrap();
+    let unrelated = context.get_atom("X11_CLIPBOARD_TEST_UNRELATED_PROPERTY").unwrap();
+
+    for &property in &[context.atoms.property, pooled, unrelated] {
+        xcb::change_property(
+            &context.connection, xcb::PROP_MODE_REPLACE as u8,
+            context.window, property, context.atoms.utf8_string, 8,
+            b"stale"
+        );
+    }
+
+    context.cleanup_properties().unwrap();
+
+    let properties = xcb::list_properties(&context.connection, context.window).get_reply().unwrap();
+    let properties = properties.atoms();
+    assert!(!properties.contains(&context.atoms.property));
+    assert!(!properties.contains(&pooled));
+    assert!(properties.contains(&unrelated));
+}
+
+#[test]
+fn it_refuse_to_load_from_own_getter() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.getter.get_atom("X11_CLIPBOARD_TEST_SELF_CONVERSION").unwrap();
+
+    xcb::set_selection_owner(&clipboard.getter.connection, clipboard.getter.window, selection, xcb::CURRENT_TIME);
+    clipboard.getter.connection.flush();
+
+    match clipboard.load(selection, clipboard.getter.atoms.utf8_string, clipboard.getter.atoms.property, Duration::from_secs(3)) {
+        Err(Error::SelfConversionUnsupported) => (),
+        other => panic!("expected self conversion error, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_serve_targeted_requestor_only() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_TARGETED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+    let other = Inspector::new().unwrap();
+
+    clipboard.store_targeted(selection, atom_utf8string, "secret", clipboard.getter.window).unwrap();
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"secret");
+    assert_eq!(other.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"");
+
+    clipboard.store_targeted_ephemeral(selection, atom_utf8string, "once", clipboard.getter.window).unwrap();
+    assert_eq!(other.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"");
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"once");
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"");
+}
+
+#[test]
+fn it_label_known_atoms() {
+    let clipboard = Clipboard::new().unwrap();
+    let atoms = &clipboard.getter.atoms;
+
+    assert_eq!(clipboard.atom_label(atoms.clipboard), "CLIPBOARD");
+    assert_eq!(clipboard.atom_label(atoms.primary), "PRIMARY");
+    assert_eq!(clipboard.atom_label(atoms.targets), "TARGETS");
+    assert_eq!(clipboard.atom_label(atoms.utf8_string), "UTF8_STRING");
+    assert_eq!(clipboard.atom_label(atoms.incr), "INCR");
+    assert_eq!(clipboard.atom_label(xcb::ATOM_ATOM), "ATOM");
+
+    let unknown = clipboard.getter.get_atom("X11_CLIPBOARD_TEST_UNLABELED").unwrap();
+    assert_eq!(clipboard.atom_label(unknown), format!("ATOM({})", unknown));
+}
+
+#[test]
+fn it_clear_sync() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_CLEAR_SYNC").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    clipboard.store(selection, atom_utf8string, "data").unwrap();
+    clipboard.clear_sync(selection).unwrap();
+
+    let owner = xcb::get_selection_owner(&clipboard.getter.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), xcb::NONE);
+    assert_eq!(clipboard.content_fingerprint(selection), None);
+}
+
+#[test]
+fn it_pause_and_resume_watch() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_WATCH_PAUSE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    let (sender, receiver) = channel();
+    let handle = clipboard.watch(selection, move |owner| sender.send(owner).unwrap()).unwrap();
+
+    handle.pause();
+    clipboard.store(selection, atom_utf8string, "while paused").unwrap();
+    assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+
+    handle.resume();
+    clipboard.clear_sync(selection).unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(3)), Ok(xcb::NONE));
+}
+
+#[test]
+fn it_benchmark_roundtrip() {
+    let mut clipboard = Clipboard::new().unwrap();
+    clipboard.set_default_timeout(Some(Duration::from_secs(3)));
+
+    clipboard.benchmark_roundtrip(64).unwrap();
+    clipboard.benchmark_roundtrip(1 << 20).unwrap();
+}
+
+#[test]
+fn it_relinquish_primary() {
+    let clipboard = Clipboard::new().unwrap();
+    let atom_primary = clipboard.setter.atoms.primary;
+
+    clipboard.store(atom_primary, clipboard.setter.atoms.utf8_string, "selected").unwrap();
+    clipboard.relinquish_primary().unwrap();
+
+    let owner = xcb::get_selection_owner(&clipboard.getter.connection, atom_primary).get_reply().unwrap();
+    assert_eq!(owner.owner(), xcb::NONE);
+}
+
+#[test]
+fn it_serve_length() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LENGTH").unwrap();
+    let timeout = Duration::from_secs(3);
+
+    assert_eq!(clipboard.load_length(selection, timeout).unwrap(), None);
+
+    clipboard.store(selection, clipboard.setter.atoms.utf8_string, "twelve bytes").unwrap();
+    assert_eq!(clipboard.load_length(selection, timeout).unwrap(), Some(12));
+    assert!(clipboard.load_target_atoms(selection, timeout).unwrap().contains(&clipboard.getter.atoms.length));
+}
+
+#[test]
+fn it_back_off_polling_for_slow_owner() {
+    let clipboard = ClipboardBuilder::new()
+        .poll_backoff(Duration::from_millis(1), Duration::from_secs(2))
+        .build()
+        .unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_POLL_BACKOFF").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store_deferred(selection, atom_utf8string, || {
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            sender.send(b"slow".to_vec()).unwrap();
+        });
+        receiver
+    }).unwrap();
+    let output = clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"slow");
+
+    // the interval grows past the timeout, but the load still stops on time.
+    let silent = Context::new(None).unwrap();
+    let selection = silent.get_atom("X11_CLIPBOARD_TEST_POLL_BACKOFF_SILENT").unwrap();
+    xcb::set_selection_owner(&silent.connection, silent.window, selection, xcb::CURRENT_TIME);
+    let owner = xcb::get_selection_owner(&silent.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), silent.window);
+
+    let start = Instant::now();
+    match clipboard.load(selection, atom_utf8string, atom_property, Duration::from_millis(600)) {
+        Err(Error::Timeout(_)) => (),
+        other => panic!("expected timeout, got {:?}", other)
+    }
+    assert!(start.elapsed() < Duration::from_millis(1500));
+}
+
+#[test]
+fn it_report_screen_info() {
+    let context = Context::new(None).unwrap();
+    let screen = context.connection.get_setup().roots().nth(context.screen as usize).unwrap();
+
+    let info = context.screen_info().unwrap();
+    assert_eq!(info.root, screen.root());
+    assert_eq!(info.width_px, screen.width_in_pixels());
+    assert_eq!(info.height_px, screen.height_in_pixels());
+    assert_eq!(info.root_visual, screen.root_visual());
+}
+
+#[test]
+fn it_clear_expired_value() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_EXPIRING").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store_expiring(selection, atom_utf8string, "secret", Duration::from_millis(300)).unwrap();
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap(), b"secret");
+
+    clipboard.wait_for_ownership_loss(selection, Duration::from_secs(3)).unwrap();
+    let owner = xcb::get_selection_owner(&clipboard.getter.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), xcb::NONE);
+
+    // replacing the value cancels the expiry.
+    clipboard.store_expiring(selection, atom_utf8string, "secret", Duration::from_millis(300)).unwrap();
+    clipboard.store(selection, atom_utf8string, "kept").unwrap();
+    thread::sleep(Duration::from_millis(600));
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap(), b"kept");
+}
+
+#[test]
+fn it_keep_ownership_quietly_on_repeated_store() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_QUIET_STORE").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let (sender, receiver) = channel();
+    let _handle = clipboard.watch(selection, move |owner| sender.send(owner).unwrap()).unwrap();
+
+    clipboard.store(selection, atom_utf8string, "first").unwrap();
+    assert_eq!(receiver.recv_timeout(Duration::from_secs(3)), Ok(clipboard.setter.window));
+
+    for i in 0..5 {
+        clipboard.store(selection, atom_utf8string, format!("update {}", i)).unwrap();
+    }
+    assert!(receiver.recv_timeout(Duration::from_millis(300)).is_err());
+
+    let output = clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"update 4");
+}
+
+#[test]
+fn it_compare_selections() {
+    let clipboard = Clipboard::new().unwrap();
+    let a = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_MATCH_A").unwrap();
+    let b = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_MATCH_B").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(a, atom_utf8string, "same").unwrap();
+    clipboard.store(b, atom_utf8string, "same").unwrap();
+    assert!(clipboard.selections_match(a, b, atom_utf8string, timeout).unwrap());
+
+    clipboard.store(b, atom_utf8string, "different").unwrap();
+    assert!(!clipboard.selections_match(a, b, atom_utf8string, timeout).unwrap());
+
+    // owned by someone else, so loaded through the server.
+    let other = Clipboard::new().unwrap();
+    other.store(b, atom_utf8string, "same").unwrap();
+    assert!(clipboard.selections_match(a, b, atom_utf8string, timeout).unwrap());
+}
+
+#[test]
+fn it_cancel_store_and_wait() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_AND_WAIT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let paster = thread::spawn(move || {
+        let inspector = Inspector::new().unwrap();
+        while inspector.owner(selection).unwrap() == xcb::NONE {
+            thread::sleep(Duration::from_millis(10));
+        }
+        inspector.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap()
+    });
+    clipboard.store_and_wait(selection, atom_utf8string, "pasted", Duration::from_secs(3), &CancelToken::new()).unwrap();
+    assert_eq!(paster.join().unwrap(), b"pasted");
+
+    let cancel = CancelToken::new();
+    let cancel2 = cancel.clone();
+    let canceller = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(100));
+        cancel2.cancel();
+    });
+    match clipboard.store_and_wait(selection, atom_utf8string, "never", Duration::from_secs(3), &cancel) {
+        Err(Error::Cancelled) => (),
+        other => panic!("expected cancel, got {:?}", other)
+    }
+    canceller.join().unwrap();
+
+    let owner = xcb::get_selection_owner(&clipboard.getter.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), xcb::NONE);
+}
+
+#[test]
+fn it_store_blocking_until_pasted() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_BLOCKING").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let paster = thread::spawn(move || {
+        let inspector = Inspector::new().unwrap();
+        while inspector.owner(selection).unwrap() == xcb::NONE {
+            thread::sleep(Duration::from_millis(10));
+        }
+        inspector.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap()
+    });
+    clipboard.store_blocking(selection, atom_utf8string, "copied", Duration::from_secs(3)).unwrap();
+    assert_eq!(paster.join().unwrap(), b"copied");
+
+    match clipboard.store_blocking(selection, atom_utf8string, "unpasted", Duration::from_millis(100)) {
+        Err(Error::Timeout(_)) => (),
+        other => panic!("expected timeout, got {:?}", other)
+    }
+    let owner = xcb::get_selection_owner(&clipboard.getter.connection, selection).get_reply().unwrap();
+    assert_eq!(owner.owner(), xcb::NONE);
+}
+
+#[test]
+fn it_load_wait_any() {
+    let clipboard = Clipboard::new().unwrap();
+    let first = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_WAIT_ANY_FIRST").unwrap();
+    let second = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_WAIT_ANY_SECOND").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let waiter = Clipboard::new().unwrap();
+        let property = waiter.getter.atoms.property;
+        let _ = sender.send(waiter.load_wait_any(&[first, second], atom_utf8string, property).unwrap());
+    });
+
+    // the waiter may not be listening yet, keep taking the selection over until it is.
+    let start = Instant::now();
+    let (selection, value) = loop {
+        clipboard.store_force(second, atom_utf8string, "either").unwrap();
+        match receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(loaded) => break loaded,
+            Err(RecvTimeoutError::Timeout) => assert!(start.elapsed() < Duration::from_secs(3)),
+            Err(err) => panic!("waiter gone: {:?}", err)
+        }
+    };
+    assert_eq!(selection, second);
+    assert_eq!(value, b"either");
+}
+
+#[test]
+fn it_end_wait_on_clear() {
+    let clipboard = Clipboard::new().unwrap();
+    let other = Context::new(None).unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    let wait_and_clear = |name: &str, fail_on_clear: bool| {
+        let selection = clipboard.setter.get_atom(name).unwrap();
+        clipboard.store(selection, atom_utf8string, "before").unwrap();
+
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            let waiter = Clipboard::new().unwrap();
+            let property = waiter.getter.atoms.property;
+            let _ = sender.send(waiter.load_wait_or_cleared(selection, atom_utf8string, property, fail_on_clear));
+        });
+
+        // the waiter may not be listening yet, keep clearing until it is.
+        let start = Instant::now();
+        loop {
+            xcb::set_selection_owner(&other.connection, xcb::NONE, selection, xcb::CURRENT_TIME);
+            other.connection.flush();
+            match receiver.recv_timeout(Duration::from_millis(100)) {
+                Ok(loaded) => break loaded,
+                Err(RecvTimeoutError::Timeout) => assert!(start.elapsed() < Duration::from_secs(3)),
+                Err(err) => panic!("waiter gone: {:?}", err)
+            }
+        }
+    };
+
+    match wait_and_clear("X11_CLIPBOARD_TEST_WAIT_CLEARED", true) {
+        Err(Error::Cleared) => (),
+        other => panic!("expected cleared, got {:?}", other)
+    }
+    assert_eq!(wait_and_clear("X11_CLIPBOARD_TEST_WAIT_CLEARED_EMPTY", false).unwrap(), b"");
+}
+
+#[test]
+fn it_wait_for_nonempty() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_NONEMPTY").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let copier = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        let empty = Clipboard::new().unwrap();
+        empty.store(selection, atom_utf8string, "").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let full = Clipboard::new().unwrap();
+        full.store(selection, atom_utf8string, "copied").unwrap();
+        thread::sleep(Duration::from_millis(500));
+    });
+
+    let output = clipboard.wait_for_nonempty(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"copied");
+    copier.join().unwrap();
+
+    match clipboard.wait_for_nonempty(selection, atom_utf8string, atom_property, Duration::from_millis(100)) {
+        Err(Error::Timeout(_)) => (),
+        other => panic!("expected timeout, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_wait_for_owner() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_WAIT_OWNER").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    match clipboard.wait_for_owner(selection, Duration::from_millis(100)) {
+        Err(Error::Timeout(info)) => assert_eq!(info.selection, selection),
+        other => panic!("expected timeout, got {:?}", other)
+    }
+
+    let (sender, receiver) = channel();
+    let copier = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        let other = Clipboard::new().unwrap();
+        other.store(selection, atom_utf8string, "owned").unwrap();
+        let _ = sender.send(other.setter.window);
+        thread::sleep(Duration::from_millis(500));
+    });
+
+    let owner = clipboard.wait_for_owner(selection, Duration::from_secs(3)).unwrap();
+    assert_eq!(owner, receiver.recv().unwrap());
+    // owned already, so it's returned at once.
+    let start = Instant::now();
+    assert_eq!(clipboard.wait_for_owner(selection, None).unwrap(), owner);
+    assert!(start.elapsed() < Duration::from_millis(500));
+    copier.join().unwrap();
+}
+
+#[test]
+fn it_count_pastes() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_PASTE_COUNT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, "counted").unwrap();
+    assert_eq!(clipboard.paste_count(selection), 0);
+
+    clipboard.load_target_atoms(selection, Duration::from_secs(3)).unwrap();
+    for _ in 0..2 {
+        clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    }
+    assert_eq!(clipboard.paste_count(selection), 2);
+
+    clipboard.store(selection, atom_utf8string, "again").unwrap();
+    assert_eq!(clipboard.paste_count(selection), 0);
+}
+
+#[test]
+fn it_export_and_import() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_EXPORT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+    let atom_property = clipboard.getter.atoms.property;
+
+    let mut targets = HashMap::new();
+    targets.insert(atom_utf8string, "exported");
+    targets.insert(atom_html, "<b>exported</b>");
+    clipboard.store_multiple(selection, targets).unwrap();
+
+    let snapshot = clipboard.export(selection, Duration::from_secs(3)).unwrap();
+    clipboard.clear(selection).unwrap();
+
+    let inspector = Inspector::new().unwrap();
+    assert_eq!(inspector.owner(selection).unwrap(), xcb::NONE);
+
+    clipboard.import(selection, snapshot).unwrap();
+
+    let names = inspector.target_names(selection, Duration::from_secs(3)).unwrap();
+    assert!(names.iter().any(|name| name == "UTF8_STRING"));
+    assert!(names.iter().any(|name| name == "text/html"));
+    assert_eq!(inspector.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap(), b"exported");
+    assert_eq!(inspector.load(selection, atom_html, atom_property, Duration::from_secs(3)).unwrap(), b"<b>exported</b>");
+}
+
+#[test]
+fn it_load_with_timestamp() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LOAD_TIMESTAMP").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_timestamp = clipboard.setter.atoms.timestamp;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, "stamped").unwrap();
+
+    // any event timestamp after the store will do, take one off a property change.
+    let context = Context::new(None).unwrap();
+    xcb::change_property(&context.connection, xcb::PROP_MODE_APPEND as u8, context.window, atom_timestamp, xcb::ATOM_INTEGER, 32, &[0u32; 0]);
+    context.connection.flush();
+    let time = loop {
+        let event = context.connection.wait_for_event().unwrap();
+        if event.response_type() & !0x80 == xcb::PROPERTY_NOTIFY {
+            break unsafe { xcb::cast_event::<xcb::PropertyNotifyEvent>(&event) }.time();
+        }
+    };
+
+    let output = clipboard.load_with_timestamp(selection, atom_utf8string, atom_property, time, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"stamped");
+
+    let output = clipboard.load_with_timestamp(selection, atom_utf8string, atom_property, xcb::CURRENT_TIME, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"stamped");
+}
+
+#[test]
+fn it_answer_timestamp() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_TIMESTAMP_TARGET").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_timestamp = clipboard.getter.atoms.timestamp;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "stamped").unwrap();
+    assert!(clipboard.load_target_atoms(selection, timeout).unwrap().contains(&atom_timestamp));
+
+    let stamp = clipboard.load(selection, atom_timestamp, atom_property, timeout).unwrap();
+    assert_eq!(stamp.len(), 4);
+    assert_ne!(u32::from_ne_bytes([stamp[0], stamp[1], stamp[2], stamp[3]]), 0);
+
+    // storing over what we own keeps the time it was acquired at.
+    clipboard.store(selection, atom_utf8string, "stamped again").unwrap();
+    assert_eq!(clipboard.load(selection, atom_timestamp, atom_property, timeout).unwrap(), stamp);
+}
+
+#[test]
+fn it_store_while_loading() {
+    let clipboard = Arc::new(Clipboard::new().unwrap());
+    let owner = Clipboard::new().unwrap();
+    let loaded = owner.setter.get_atom("X11_CLIPBOARD_TEST_STORE_WHILE_LOADING").unwrap();
+    let stored = owner.setter.get_atom("X11_CLIPBOARD_TEST_STORE_WHILE_LOADING_OTHER").unwrap();
+    let atom_utf8string = owner.setter.atoms.utf8_string;
+    owner.store(loaded, atom_utf8string, "loaded").unwrap();
+
+    // stores must leave the events of a load on the same connection alone.
+    let loader = {
+        let clipboard = Arc::clone(&clipboard);
+        thread::spawn(move || {
+            for _ in 0..20 {
+                let output = clipboard.load(loaded, atom_utf8string, clipboard.getter.atoms.property, Duration::from_secs(3)).unwrap();
+                assert_eq!(output, b"loaded");
+            }
+        })
+    };
+    for i in 0..20 {
+        clipboard.store(stored, atom_utf8string, format!("stored {}", i)).unwrap();
+        clipboard.clear(stored).unwrap();
+    }
+    loader.join().unwrap();
+}
+
+#[test]
+fn it_try_load() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_TRY_LOAD").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    assert_eq!(clipboard.try_load(selection, atom_utf8string, atom_property).unwrap(), None);
+
+    clipboard.store(selection, atom_utf8string, "polled").unwrap();
+    assert_eq!(clipboard.try_load(selection, atom_utf8string, atom_property).unwrap(), Some(b"polled".to_vec()));
+
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+    assert_eq!(clipboard.try_load(selection, atom_html, atom_property).unwrap(), None);
+}
+
+#[test]
+fn it_call_back_on_paste() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_CALLBACK").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let (sender, receiver) = channel();
+    clipboard.store_with_callback(selection, atom_utf8string, "watched", move |requestor| sender.send(requestor).unwrap()).unwrap();
+
+    for _ in 0..2 {
+        clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+        assert_eq!(receiver.recv_timeout(Duration::from_secs(3)).unwrap(), clipboard.getter.window);
+    }
+
+    // losing the selection drops the callback along with the sender.
+    let other = Clipboard::new().unwrap();
+    other.store(selection, atom_utf8string, "taken").unwrap();
+    match receiver.recv_timeout(Duration::from_secs(3)) {
+        Err(RecvTimeoutError::Disconnected) => (),
+        other => panic!("expected the callback dropped, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_connect_to_display() {
+    let display = ::std::env::var("DISPLAY").unwrap();
+    let clipboard = Clipboard::with_display(&display).unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DISPLAY").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, "displayed").unwrap();
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap(), b"displayed");
+
+    assert!(Clipboard::with_display("no-such-host:4242").is_err());
+}
+
+#[test]
+fn it_load_typed() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LOAD_TYPED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_text = clipboard.setter.get_atom("TEXT").unwrap();
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "typed").unwrap();
+
+    assert_eq!(clipboard.load_typed(selection, atom_text, atom_property, timeout).unwrap(), (atom_utf8string, b"typed".to_vec()));
+    assert_eq!(clipboard.load_typed(selection, atom_utf8string, atom_property, timeout).unwrap(), (atom_utf8string, b"typed".to_vec()));
+
+    let atom_html = clipboard.setter.get_atom("text/html").unwrap();
+    assert_eq!(clipboard.load_typed(selection, atom_html, atom_property, timeout).unwrap(), (xcb::ATOM_NONE, Vec::new()));
+}
+
+#[test]
+fn it_stream_watch_events() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_WATCH_EVENTS").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+
+    let events = clipboard.watch_events(selection).unwrap();
+
+    let other = Clipboard::new().unwrap();
+    other.store(selection, atom_utf8string, "first").unwrap();
+    let event = events.recv_timeout(Duration::from_secs(3)).unwrap();
+    assert_eq!(event, WatchEvent { selection, owner: other.setter.window });
+
+    clipboard.store(selection, atom_utf8string, "second").unwrap();
+    let event = events.recv_timeout(Duration::from_secs(3)).unwrap();
+    assert_eq!(event, WatchEvent { selection, owner: clipboard.setter.window });
+
+    drop(events);
+}
+
+#[test]
+fn it_build_with_poll_duration() {
+    let clipboard = ClipboardBuilder::new()
+        .display(None)
+        .poll_duration(Duration::from_millis(5))
+        .incr_chunk_size(INCR_CHUNK_SIZE * 2)
+        .build()
+        .unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_POLL_DURATION").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(selection, atom_utf8string, "built").unwrap();
+    let output = clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, b"built");
+}
+
+#[test]
+fn it_store_shared() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_SHARED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    let value = Arc::new(String::from("shared"));
+    clipboard.store_shared(selection, atom_utf8string, Arc::clone(&value)).unwrap();
+    let output = clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap();
+    assert_eq!(output, value.as_bytes());
+}
+
+#[test]
+fn it_clear_all() {
+    let clipboard = Clipboard::new().unwrap();
+    let first = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_CLEAR_ALL_FIRST").unwrap();
+    let second = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_CLEAR_ALL_SECOND").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+
+    clipboard.store(first, atom_utf8string, "first").unwrap();
+    clipboard.store(second, atom_utf8string, "second").unwrap();
+
+    clipboard.clear_all().unwrap();
+
+    let inspector = Inspector::new().unwrap();
+    for &selection in &[first, second] {
+        assert_eq!(inspector.owner(selection).unwrap(), xcb::NONE);
+        assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, Duration::from_secs(3)).unwrap(), b"");
+    }
+}
+
+#[test]
+fn it_notice_dead_worker() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DEAD_WORKER").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    assert!(clipboard.is_alive());
+
+    // have the server close the setter's connection under the worker.
+    let killer = Context::new(None).unwrap();
+    xcb::kill_client(&killer.connection, clipboard.setter.window);
+    killer.connection.flush();
+
+    let start = Instant::now();
+    while clipboard.is_alive() && start.elapsed() < Duration::from_secs(3) {
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(!clipboard.is_alive());
+
+    match clipboard.store(selection, atom_utf8string, "nobody") {
+        Err(Error::WorkerDead) => (),
+        other => panic!("expected a dead worker, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_reconnect_worker_on_loss() {
+    let clipboard = ClipboardBuilder::new()
+        .reconnect_on_loss(5, Duration::from_millis(50))
+        .build()
+        .unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_RECONNECT_ON_LOSS").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "before").unwrap();
+
+    let inspector = Inspector::new().unwrap();
+    xcb::kill_client(&inspector.context.connection, clipboard.setter.window);
+    inspector.context.connection.flush();
+
+    // taken back by the fresh window once the worker reconnected.
+    let start = Instant::now();
+    loop {
+        let owner = inspector.owner(selection).unwrap();
+        if owner != xcb::NONE && owner != clipboard.setter.window { break };
+        assert!(start.elapsed() < timeout, "selection wasn't taken back");
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(clipboard.is_alive());
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"before");
+
+    clipboard.store(selection, atom_utf8string, "after").unwrap();
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"after");
+}
+
+#[test]
+fn it_check_for_target() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_HAS_TARGET").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_png = clipboard.setter.get_atom("image/png").unwrap();
+    let timeout = Duration::from_secs(3);
+
+    assert!(!clipboard.has_target(selection, atom_utf8string, timeout).unwrap());
+
+    clipboard.store(selection, atom_utf8string, "text").unwrap();
+    assert!(clipboard.has_target(selection, atom_utf8string, timeout).unwrap());
+    assert!(!clipboard.has_target(selection, atom_png, timeout).unwrap());
+}
+
+#[test]
+fn it_intern_atoms_in_bulk() {
+    let context = Context::new(None).unwrap();
+    let names = (0..10)
+        .map(|i| format!("X11_CLIPBOARD_TEST_BULK_ATOM_{}", i))
+        .collect::<Vec<_>>();
+    let names = names.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let atoms = context.get_atoms(&names, false).unwrap();
+    assert_eq!(atoms.len(), names.len());
+    for (&name, &atom) in names.iter().zip(&atoms) {
+        assert_eq!(context.get_atom(name).unwrap(), atom);
+    }
+
+    assert_eq!(context.get_atoms(&names, true).unwrap(), atoms);
+    assert_eq!(context.get_atoms(&["X11_CLIPBOARD_TEST_BULK_ATOM_NEVER_INTERNED"], true).unwrap(), vec![xcb::ATOM_NONE]);
+}
+
+#[test]
+fn it_intern_extra_atoms_up_front() {
+    let clipboard = ClipboardBuilder::new()
+        .extra_atoms(&["text/html", "X11_CLIPBOARD_TEST_EXTRA_ATOM"])
+        .build()
+        .unwrap();
+
+    for &name in &["text/html", "X11_CLIPBOARD_TEST_EXTRA_ATOM"] {
+        let atom = clipboard.getter.get_atom(name).unwrap();
+        assert_eq!(clipboard.getter.atoms.extra(name), Some(atom));
+        assert_eq!(clipboard.setter.atoms.extra(name), Some(atom));
+    }
+    assert_eq!(clipboard.getter.atoms.extra("image/png"), None);
+    assert_eq!(Context::new(None).unwrap().atoms.extra("text/html"), None);
+}
+
+#[test]
+fn it_store_text_for_old_clients() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_STORE_TEXT").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+    let inspector = Inspector::new().unwrap();
+
+    clipboard.store_text(selection, "plain").unwrap();
+    let names = inspector.target_names(selection, timeout).unwrap();
+    assert!(names.iter().any(|name| name == "UTF8_STRING"));
+    assert!(names.iter().any(|name| name == "STRING"));
+
+    clipboard.store_text(selection, "café").unwrap();
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), "café".as_bytes());
+    assert_eq!(clipboard.load(selection, atom_string, atom_property, timeout).unwrap(), b"caf\xe9");
+
+    clipboard.store_text(selection, "日本").unwrap();
+    let names = inspector.target_names(selection, timeout).unwrap();
+    assert!(names.iter().any(|name| name == "UTF8_STRING"));
+    assert!(!names.iter().any(|name| name == "STRING"));
+}
+
+#[test]
+fn it_store_image() {
+    let png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR fake image data".to_vec();
+
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_IMAGE").unwrap();
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store_image(selection, "image/png", png.clone()).unwrap();
+    assert_eq!(clipboard.load_image(selection, "image/png", timeout).unwrap(), png);
+    assert_eq!(clipboard.load_image(selection, "image/jpeg", timeout).unwrap(), b"");
+}
+
+#[test]
+fn it_load_first_offered_target() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LOAD_ANY").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_png = clipboard.setter.get_atom("image/png").unwrap();
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_string, "latin1").unwrap();
+
+    let output = clipboard.load_any(selection, &[atom_utf8string, atom_string], atom_property, timeout).unwrap();
+    assert_eq!(output, (atom_string, b"latin1".to_vec()));
+
+    match clipboard.load_any(selection, &[atom_png], atom_property, timeout) {
+        Err(Error::NoMatchingTarget) => (),
+        other => panic!("expected no matching target, got {:?}", other)
+    }
+}
+
+#[test]
+fn it_load_string() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_LOAD_STRING").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_string = clipboard.setter.atoms.string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store(selection, atom_utf8string, "日本").unwrap();
+    assert_eq!(clipboard.load_string(selection, atom_property, timeout).unwrap(), "日本");
+
+    clipboard.store(selection, atom_string, &b"caf\xe9"[..]).unwrap();
+    assert_eq!(clipboard.load_string(selection, atom_property, timeout).unwrap(), "café");
+
+    clipboard.store(selection, atom_utf8string, &b"caf\xe9"[..]).unwrap();
+    match clipboard.load_string(selection, atom_property, timeout) {
+        Err(Error::InvalidUtf8(_)) => (),
+        other => panic!("expected invalid UTF-8, got {:?}", other)
+    }
+}
+
+/// data whose drop panics, to poison the lock it's dropped under.
+struct PanicOnDrop(Vec<u8>);
+
+impl AsRef<[u8]> for PanicOnDrop {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Drop for PanicOnDrop {
+    fn drop(&mut self) {
+        if !thread::panicking() {
+            panic!("dropped while stored over");
+        }
+    }
+}
+
+#[test]
+fn it_serve_after_poisoned_setmap() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_POISONED").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+
+    clipboard.store_shared(selection, atom_utf8string, Arc::new(PanicOnDrop(b"doomed".to_vec()))).unwrap();
+    let stored_over = panic::catch_unwind(AssertUnwindSafe(|| {
+        clipboard.store(selection, atom_utf8string, "survivor").unwrap();
+    }));
+    assert!(stored_over.is_err());
+
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"survivor");
+    clipboard.store(selection, atom_utf8string, "still serving").unwrap();
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"still serving");
+}
+
+#[test]
+fn it_read_property_range() {
+    let context = Context::new(None).unwrap();
+    let property = context.get_atom("X11_CLIPBOARD_TEST_PROPERTY_RANGE").unwrap();
+    let data = (0..64u8).collect::<Vec<_>>();
+
+    xcb::change_property(&context.connection, xcb::PROP_MODE_REPLACE as u8, context.window, property, context.atoms.utf8_string, 8, &data);
+    assert_eq!(context.get_property_range(property, 0, 4).unwrap(), (context.atoms.utf8_string, data[..16].to_vec()));
+    assert_eq!(context.get_property_range(property, 15, 4).unwrap().1, &data[60..]);
+    assert_eq!(context.get_property_range(property, 0, u32::MAX).unwrap().1, data);
+
+    xcb::delete_property(&context.connection, context.window, property);
+    assert_eq!(context.get_property_range(property, 0, 4).unwrap(), (xcb::ATOM_NONE, Vec::new()));
+}
+
+#[test]
+fn it_serve_per_requestor() {
+    let clipboard = Clipboard::new().unwrap();
+    let selection = clipboard.setter.get_atom("X11_CLIPBOARD_TEST_DYNAMIC").unwrap();
+    let atom_utf8string = clipboard.setter.atoms.utf8_string;
+    let atom_property = clipboard.getter.atoms.property;
+    let timeout = Duration::from_secs(3);
+    let trusted = clipboard.getter.window;
+
+    clipboard.store_dynamic(selection, atom_utf8string, move |requestor, target| {
+        assert_eq!(target, atom_utf8string);
+        match requestor {
+            window if window == trusted => Some(b"everything".to_vec()),
+            _ => None
+        }
+    }).unwrap();
+
+    assert_eq!(clipboard.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"everything");
+
+    // anyone else is refused.
+    let inspector = Inspector::new().unwrap();
+    assert_eq!(inspector.load(selection, atom_utf8string, atom_property, timeout).unwrap(), b"");
+}